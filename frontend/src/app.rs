@@ -1,28 +1,45 @@
 use anyhow::Error;
 use log::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use yew::services::websocket::{WebSocketService, WebSocketStatus, WebSocketTask};
+use yew::services::{timeout::TimeoutTask, TimeoutService};
 use yew::{format::Bincode, props};
 use yew::{html, Component, ComponentLink, Html, ShouldRender};
 use yewtil::{
+    future::LinkFuture,
     ptr::{Irc, Mrc},
     NeqAssign,
 };
 
 use shared::*;
 
+use crate::asset;
 use crate::components::windowmanager::{ChangePanel, LeftPanel};
 use crate::components::{
     chat::Chat,
+    chat_info::ChatInfoPanel,
+    chat_picker::ChatPicker,
     chatlist::Chatlist,
+    contact::ContactPanel,
     create_chat::CreateChat,
+    errors::{Errors, RequestFailure},
+    message_info::MessageInfoPanel,
     messages::Props as MessagesProps,
     modal::Modal,
+    search::Search,
+    settings::Settings,
     sidebar::Sidebar,
     windowmanager::{Props as WindowManagerProps, WindowManager},
 };
 
+/// How long to wait before connecting again, after the connection was lost.
+const RECONNECT_DELAY_SECS: u64 = 2;
+/// How long editing the draft has to pause before it is saved.
+const DRAFT_SAVE_DELAY_SECS: u64 = 2;
+
 #[derive(Debug)]
 pub enum WsAction {
     Connect,
@@ -30,8 +47,48 @@ pub enum WsAction {
     Lost,
 }
 
+#[wasm_bindgen(module = "/src/js/tauri_wrapper.js")]
+extern "C" {
+    async fn invoke_backend_secret() -> JsValue;
+    #[wasm_bindgen(catch)]
+    async fn invoke_backend_url() -> Result<JsValue, JsValue>;
+    fn is_tauri() -> bool;
+    fn location_token() -> String;
+    #[wasm_bindgen(catch)]
+    async fn fetch_backend_url() -> Result<JsValue, JsValue>;
+}
+
+/// Asks the backend where to connect to.
+async fn fetch_endpoint() -> Result<String, String> {
+    if !is_tauri() {
+        // served by `dc40-server`, which hands out the secret in the url
+        let url = unsafe { fetch_backend_url().await }
+            .map_err(|err| err.as_string().unwrap_or_default())?;
+        let token = location_token();
+        asset::set_token(token.clone());
+
+        return Ok(format!(
+            "{}?token={}",
+            url.as_string().unwrap_or_default(),
+            token
+        ));
+    }
+
+    let url =
+        unsafe { invoke_backend_url().await }.map_err(|err| err.as_string().unwrap_or_default())?;
+    // the backend only serves clients that know its secret
+    let secret = unsafe { invoke_backend_secret().await };
+
+    Ok(format!(
+        "{}?token={}",
+        url.as_string().unwrap_or_default(),
+        secret.as_string().unwrap_or_default()
+    ))
+}
+
 #[derive(Debug)]
 pub enum Msg {
+    Endpoint(Result<String, String>),
     Connected,
     WsAction(WsAction),
     WsReady(Result<Response, Error>),
@@ -41,6 +98,28 @@ pub enum Msg {
     CancelAccountCreation,
     AccountCreation(String, String),
     ChangePanel(ChangePanel),
+    DismissRequestFailure(usize),
+    RetryError(ErrorInfo),
+    SendMessage(String),
+    Reply(Option<InnerChatMessage>),
+    /// Opens the chat picker to forward the messages.
+    Forward(Vec<u32>),
+    ForwardTo(u32),
+    DraftInput(String),
+    RemoveDraftFile,
+    SaveDraft,
+    LoadChatList(usize, usize),
+    FilterChats(String),
+    ChangeChatListFlags(ChatListFlags),
+    ShowArchived(bool),
+    Search(String, Option<u32>),
+    CloseSearch,
+    CloseMessageInfo,
+    ShowContact(u32),
+    CloseContact,
+    ShowChatInfo,
+    CloseChatInfo,
+    ShowSettings,
 }
 
 impl From<WsAction> for Msg {
@@ -53,12 +132,23 @@ pub struct App {
     link: ComponentLink<App>,
     model: Model,
     ws: Option<WebSocketTask>,
+    endpoint: Option<String>,
+    next_request_id: u32,
+    pending_requests: HashMap<u32, &'static str>,
+    capabilities: HashSet<String>,
+    /// The last event received, to catch up after reconnecting.
+    last_event_seq: Option<u64>,
+    /// The run of the backend `last_event_seq` belongs to.
+    backend_run_id: Option<u64>,
+    reconnect: Option<TimeoutTask>,
+    /// Saves the draft once editing pauses.
+    draft_timeout: Option<TimeoutTask>,
 }
 
 #[derive(Debug, Clone, Default)]
 struct Model {
     accounts: Mrc<HashMap<u32, SharedAccountState>>,
-    errors: Mrc<Vec<String>>,
+    errors: Mrc<Vec<ErrorInfo>>,
     selected_account: Mrc<Option<u32>>,
     selected_chat_id: Mrc<Option<u32>>,
     selected_chat: Mrc<Option<ChatState>>,
@@ -66,26 +156,51 @@ struct Model {
     chats: Mrc<Vec<ChatState>>,
     chats_range: Mrc<(usize, usize)>,
     chats_len: Mrc<usize>,
+    archived_len: Mrc<usize>,
     messages_range: Mrc<(usize, usize)>,
     message_items: Mrc<Vec<ChatItem>>,
     messages: Mrc<Vec<ChatMessage>>,
     show_account_creation: bool,
     left_panel: LeftPanel,
     contacts: Mrc<Option<Vec<ContactInfo>>>,
+    request_failures: Mrc<Vec<RequestFailure>>,
+    /// The message the next one sent quotes.
+    reply_to: Mrc<Option<InnerChatMessage>>,
+    /// The messages waiting for the user to pick a chat to forward them to.
+    forward_msg_ids: Mrc<Vec<u32>>,
+    draft_text: Mrc<String>,
+    draft_file: Mrc<Option<Attachment>>,
+    /// The draft of the selected chat as stored in the backend.
+    saved_draft: Option<Draft>,
+    /// Narrows the chat list down.
+    chat_list_query: Mrc<String>,
+    chat_list_flags: Mrc<ChatListFlags>,
+    /// The last query and what it found.
+    search_results: Mrc<Option<(String, Vec<SearchResult>)>>,
+    /// Shown in the right panel.
+    message_info: Mrc<Option<MessageInfo>>,
+    /// Shown in the right panel, instead of the message info.
+    contact_detail: Mrc<Option<ContactInfo>>,
+    /// Whether the right panel shows the selected chat.
+    show_chat_info: Mrc<bool>,
+    /// The members of the selected chat, loaded for the chat info.
+    chat_members: Mrc<Option<Vec<ContactInfo>>>,
+    /// The blocked contacts of the selected account, loaded for the settings.
+    blocked_contacts: Mrc<Option<Vec<ContactInfo>>>,
 }
 
 impl App {
     fn view_data(&self) -> Html {
         let link = self.link.clone();
-        let send_message =
-            link.callback(move |text| Msg::WsRequest(Request::SendTextMessage { text }));
+        let send_message = link.callback(Msg::SendMessage);
+        let reply_callback = link.callback(|message| Msg::Reply(Some(message)));
+        let cancel_reply_callback = link.callback(|_| Msg::Reply(None));
+        let forward_callback = link.callback(|msg_id| Msg::Forward(vec![msg_id]));
+        let draft_input_callback = link.callback(Msg::DraftInput);
+        let cancel_file_callback = link.callback(|_| Msg::RemoveDraftFile);
 
-        let chats_fetch_callback = link.callback(move |(start_index, stop_index)| {
-            Msg::WsRequest(Request::LoadChatList {
-                start_index,
-                stop_index,
-            })
-        });
+        let chats_fetch_callback = link
+            .callback(move |(start_index, stop_index)| Msg::LoadChatList(start_index, stop_index));
         let messages_fetch_callback = link.callback(move |(start_index, stop_index)| {
             Msg::WsRequest(Request::LoadMessageList {
                 start_index,
@@ -111,7 +226,20 @@ impl App {
             Msg::WsRequest(Request::UnarchiveChat { account, chat_id })
         });
 
+        let mute_chat_callback = link.callback(move |(account, chat_id, duration)| {
+            Msg::WsRequest(Request::MuteChat {
+                account,
+                chat_id,
+                duration,
+            })
+        });
+
         let create_chat_callback = link.callback(|_| Msg::CreateChat);
+        let search_callback =
+            link.callback(|_| Msg::ChangePanel(ChangePanel::Left(LeftPanel::Search)));
+        let filter_callback = link.callback(Msg::FilterChats);
+        let flags_callback = link.callback(Msg::ChangeChatListFlags);
+        let show_archived_callback = link.callback(Msg::ShowArchived);
 
         let create_account_callback = link.callback(move |_| Msg::ShowAccountCreation);
         let cancel_account_create_callback = link.callback(move |_| Msg::CancelAccountCreation);
@@ -120,7 +248,7 @@ impl App {
             link.callback(move |(email, password)| Msg::AccountCreation(email, password));
 
         let import_callback =
-            link.callback(move |id| Msg::WsRequest(Request::GetAccountDetail { id }));
+            link.callback(move |account| Msg::WsRequest(Request::SelectAccount { account }));
 
         let select_account_callback = link.callback(move |account| {
             info!("Account switched {}", account);
@@ -159,6 +287,26 @@ impl App {
                     chat_id,
                 })
             });
+            let set_ephemeral_timer_callback = link.callback(move |timer| {
+                Msg::WsRequest(Request::SetEphemeralTimer {
+                    account: selected_account,
+                    chat_id,
+                    timer,
+                })
+            });
+            let delete_callback = link.callback(move |msg_id| {
+                Msg::WsRequest(Request::DeleteMessages {
+                    account: selected_account,
+                    msg_ids: vec![msg_id],
+                })
+            });
+            let info_callback = link.callback(move |msg_id| {
+                Msg::WsRequest(Request::GetMessageInfo {
+                    account: selected_account,
+                    msg_id,
+                })
+            });
+            let contact_callback = link.callback(Msg::ShowContact);
             let messages_props = props! {
                 MessagesProps {
                     messages: self.model.messages.irc(),
@@ -166,7 +314,11 @@ impl App {
                     messages_range: self.model.messages_range.irc(),
                     selected_chat_id: self.model.selected_chat_id.irc(),
                     fetch_callback: messages_fetch_callback,
-
+                    reply_callback,
+                    delete_callback,
+                    forward_callback,
+                    info_callback,
+                    contact_callback: contact_callback.clone(),
                 }
             };
 
@@ -177,6 +329,15 @@ impl App {
                     send_message = send_message
                     messages_props = messages_props
                     selected_chat=self.model.selected_chat.clone()
+                    reply_to=self.model.reply_to.irc()
+                    cancel_reply_callback=cancel_reply_callback
+                    draft_text=self.model.draft_text.irc()
+                    draft_input_callback=draft_input_callback
+                    draft_file=self.model.draft_file.irc()
+                    cancel_file_callback=cancel_file_callback
+                    set_ephemeral_timer_callback=set_ephemeral_timer_callback
+                    contact_callback=contact_callback
+                    chat_info_callback=link.callback(|_| Msg::ShowChatInfo)
                 />
             )
         } else {
@@ -188,18 +349,21 @@ impl App {
         let create_chat_cb = link.callback(|users| Msg::WsRequest(Request::CreateChat(users)));
         let create_group_chat_cb =
             link.callback(|(users, name)| Msg::WsRequest(Request::CreateGroupChat(users, name)));
+        let create_contact_cb =
+            link.callback(|(name, email)| Msg::WsRequest(Request::CreateContact { name, email }));
         let add_chat_close_cb =
             link.callback(|_| Msg::ChangePanel(ChangePanel::Left(LeftPanel::Chats)));
 
         let left = match self.model.left_panel {
-            LeftPanel::Chats => {
+            LeftPanel::Chats | LeftPanel::Archived => {
                 html!(
                     <div class="normal-panel">
                         <Sidebar
                             accounts=self.model.accounts.irc()
                             selected_account=self.model.selected_account.irc()
                             select_account_callback=select_account_callback
-                            create_account_callback=create_account_callback/>
+                            create_account_callback=create_account_callback
+                            settings_callback=link.callback(|_| Msg::ShowSettings) />
 
                         <Chatlist
                             selected_account=self.model.selected_account.irc()
@@ -212,11 +376,19 @@ impl App {
                             unpin_chat_callback=unpin_chat_callback
                             archive_chat_callback=archive_chat_callback
                             unarchive_chat_callback=unarchive_chat_callback
+                            mute_chat_callback=mute_chat_callback
                             chats=self.model.chats.irc()
                             chats_range=self.model.chats_range.irc()
                             chats_len=self.model.chats_len.irc()
                             fetch_callback=chats_fetch_callback
-                            create_chat_callback=create_chat_callback/>
+                            create_chat_callback=create_chat_callback
+                            search_callback=search_callback
+                            chat_list_query=self.model.chat_list_query.irc()
+                            chat_list_flags=self.model.chat_list_flags.irc()
+                            filter_callback=filter_callback
+                            flags_callback=flags_callback
+                            archived_len=self.model.archived_len.irc()
+                            show_archived_callback=show_archived_callback/>
                     </div>
                 )
             }
@@ -225,29 +397,291 @@ impl App {
                     <CreateChat
                         create_group_chat_cb=create_group_chat_cb
                         create_chat_cb=create_chat_cb
+                        create_contact_cb=create_contact_cb
                         contacts=self.model.contacts.irc()
                         contact_cb=load_contacts
                         add_chat_close_cb=add_chat_close_cb />
                 }
             }
+            LeftPanel::Search => {
+                let search_callback = link.callback(|(query, chat_id)| Msg::Search(query, chat_id));
+                let select_callback = link.callback(move |chat_id| {
+                    Msg::WsRequest(Request::SelectChat {
+                        account: selected_account,
+                        chat_id,
+                    })
+                });
+                let close_callback = link.callback(|_| Msg::CloseSearch);
+
+                html! {
+                    <Search
+                        results=self.model.search_results.irc()
+                        selected_chat=self.model.selected_chat.irc()
+                        search_callback=search_callback
+                        select_callback=select_callback
+                        close_callback=close_callback />
+                }
+            }
+            LeftPanel::Settings => {
+                let unblock_callback = link.callback(move |contact_id| {
+                    Msg::WsRequest(Request::UnblockContact {
+                        account: selected_account,
+                        contact_id,
+                    })
+                });
+                let close_callback =
+                    link.callback(|_| Msg::ChangePanel(ChangePanel::Left(LeftPanel::Chats)));
+
+                html! {
+                    <Settings
+                        blocked_contacts=self.model.blocked_contacts.irc()
+                        unblock_callback=unblock_callback
+                        close_callback=close_callback />
+                }
+            }
+            LeftPanel::Forward => {
+                let pick_callback = link.callback(Msg::ForwardTo);
+                let close_callback =
+                    link.callback(|_| Msg::ChangePanel(ChangePanel::Left(LeftPanel::Chats)));
+
+                html! {
+                    <ChatPicker
+                        title="Forward to".to_string()
+                        chats=self.model.chats.irc()
+                        chats_range=self.model.chats_range.irc()
+                        chats_len=self.model.chats_len.irc()
+                        fetch_callback=chats_fetch_callback
+                        pick_callback=pick_callback
+                        close_callback=close_callback />
+                }
+            }
+        };
+
+        let right = if let Some(contact) = &*self.model.contact_detail {
+            let contact_id = contact.id;
+            let rename_callback = link.callback(move |name| {
+                Msg::WsRequest(Request::RenameContact {
+                    account: selected_account,
+                    contact_id,
+                    name,
+                })
+            });
+            let close_callback = link.callback(|_| Msg::CloseContact);
+            Some(html! {
+                <ContactPanel
+                    contact=contact.clone()
+                    rename_callback=rename_callback
+                    close_callback=close_callback />
+            })
+        } else if let (true, Some(chat)) = (*self.model.show_chat_info, &*self.model.selected_chat)
+        {
+            Some(self.view_chat_info(selected_account, chat))
+        } else {
+            self.model.message_info.as_ref().as_ref().map(|info| {
+                let close_callback = link.callback(|_| Msg::CloseMessageInfo);
+                html! {
+                    <MessageInfoPanel info=info.clone() close_callback=close_callback />
+                }
+            })
         };
 
         let file_manager_props = props! {
             WindowManagerProps {
                 left,
                 center: messages,
-                right: None,
+                right,
                 left_type: self.model.left_panel.clone()
             }
         };
 
+        let dismiss_error_callback =
+            link.callback(|id| Msg::WsRequest(Request::DismissError { id }));
+        let retry_error_callback = link.callback(Msg::RetryError);
+        let dismiss_request_failure_callback = link.callback(Msg::DismissRequestFailure);
+
         html! {
             <>
                 {account_creation_modal}
                 <WindowManager with file_manager_props/>
+                <Errors
+                    accounts=self.model.accounts.irc()
+                    errors=self.model.errors.irc()
+                    failures=self.model.request_failures.irc()
+                    dismiss_error_callback=dismiss_error_callback
+                    retry_error_callback=retry_error_callback
+                    dismiss_failure_callback=dismiss_request_failure_callback />
             </>
         }
     }
+
+    fn view_chat_info(&self, account: u32, chat: &ChatState) -> Html {
+        let link = &self.link;
+        let chat_id = chat.id;
+        let load_contacts_callback = link.callback(|_| Msg::WsRequest(Request::GetContacts));
+        let rename_callback = link.callback(move |name| {
+            Msg::WsRequest(Request::RenameChat {
+                account,
+                chat_id,
+                name,
+            })
+        });
+        let set_image_callback = link.callback(move |image| {
+            Msg::WsRequest(Request::SetChatProfileImage {
+                account,
+                chat_id,
+                image,
+            })
+        });
+        let add_member_callback = link.callback(move |contact_id| {
+            Msg::WsRequest(Request::AddChatMember {
+                account,
+                chat_id,
+                contact_id,
+            })
+        });
+        let remove_member_callback = link.callback(move |contact_id| {
+            Msg::WsRequest(Request::RemoveChatMember {
+                account,
+                chat_id,
+                contact_id,
+            })
+        });
+        let show_contact_callback = link.callback(Msg::ShowContact);
+        let message_privately_callback = link.callback(|contact_id| {
+            let mut contacts = HashSet::new();
+            contacts.insert(contact_id);
+            Msg::WsRequest(Request::CreateChat(contacts))
+        });
+        let leave_callback =
+            link.callback(move |_| Msg::WsRequest(Request::LeaveChat { account, chat_id }));
+        let close_callback = link.callback(|_| Msg::CloseChatInfo);
+
+        html! {
+            <ChatInfoPanel
+                chat=chat.clone()
+                members=self.model.chat_members.irc()
+                contacts=self.model.contacts.irc()
+                load_contacts_callback=load_contacts_callback
+                rename_callback=rename_callback
+                set_image_callback=set_image_callback
+                add_member_callback=add_member_callback
+                remove_member_callback=remove_member_callback
+                show_contact_callback=show_contact_callback
+                message_privately_callback=message_privately_callback
+                leave_callback=leave_callback
+                close_callback=close_callback />
+        }
+    }
+
+    /// Requests the blocked contacts of the selected account.
+    fn load_blocked_contacts(&self) {
+        if let Some(account) = *self.model.selected_account {
+            self.link
+                .send_message(Msg::WsRequest(Request::GetBlockedContacts { account }));
+        }
+    }
+
+    /// Requests the members of the selected chat.
+    fn load_chat_members(&self) {
+        if let (Some(account), Some(chat_id)) =
+            (*self.model.selected_account, *self.model.selected_chat_id)
+        {
+            self.link
+                .send_message(Msg::WsRequest(Request::GetChatMembers { account, chat_id }));
+        }
+    }
+
+    /// Stores the draft of the chat that was shown, and restores the one of the newly
+    /// selected chat.
+    fn change_chat(&mut self, account: Option<u32>, chat_id: Option<u32>, chat: Option<ChatState>) {
+        let previous = (*self.model.selected_account, *self.model.selected_chat_id);
+        let account_changed = self.model.selected_account.neq_assign(account);
+        let chat_changed = self.model.selected_chat_id.neq_assign(chat_id);
+
+        if account_changed && self.model.left_panel == LeftPanel::Settings {
+            self.model.blocked_contacts.neq_assign(None);
+            self.load_blocked_contacts();
+        }
+
+        if account_changed || chat_changed {
+            self.draft_timeout = None;
+            if let (Some(account), Some(chat_id)) = previous {
+                self.save_draft(account, chat_id);
+            }
+
+            let draft = chat.as_ref().and_then(|chat| chat.draft.clone());
+            let (text, quote, file) = match draft.clone() {
+                Some(Draft { text, quote, file }) => (text, quote.map(|quote| *quote), file),
+                None => (None, None, None),
+            };
+            self.model.draft_text.neq_assign(text.unwrap_or_default());
+            self.model.draft_file.neq_assign(file);
+            self.model.reply_to.neq_assign(quote);
+            self.model.saved_draft = draft;
+            self.model.message_info.neq_assign(None);
+            self.model.contact_detail.neq_assign(None);
+            self.model.show_chat_info.neq_assign(false);
+            self.model.chat_members.neq_assign(None);
+        }
+
+        self.model.selected_chat.neq_assign(chat);
+    }
+
+    /// Sends the draft to the backend, if it changed since it was loaded.
+    fn save_draft(&mut self, account: u32, chat_id: u32) {
+        let text = Some(self.model.draft_text.clone_inner()).filter(|text| !text.is_empty());
+        let quote_msg_id = self.model.reply_to.as_ref().as_ref().map(|quote| quote.id);
+        let file = self.model.draft_file.clone_inner();
+
+        let saved = self
+            .model
+            .saved_draft
+            .as_ref()
+            .map(|draft| {
+                (
+                    draft.text.clone(),
+                    draft.quote.as_ref().map(|quote| quote.id),
+                    draft.file.clone(),
+                )
+            })
+            .unwrap_or_default();
+        if saved == (text.clone(), quote_msg_id, file.clone()) {
+            return;
+        }
+
+        self.model.saved_draft = Some(Draft {
+            text: text.clone(),
+            quote: self.model.reply_to.clone_inner().map(Box::new),
+            file: file.clone(),
+        });
+        self.link.send_message(Msg::WsRequest(Request::SetDraft {
+            account,
+            chat_id,
+            text,
+            quote_msg_id,
+            file,
+        }));
+    }
+
+    /// Saves the draft once editing pauses, replacing the task cancels the previous one.
+    fn schedule_draft_save(&mut self) {
+        let callback = self.link.callback(|_| Msg::SaveDraft);
+        self.draft_timeout = Some(TimeoutService::spawn(
+            Duration::from_secs(DRAFT_SAVE_DELAY_SECS),
+            callback,
+        ));
+    }
+
+    /// Tries to connect again after a while, unless that is already scheduled.
+    fn schedule_reconnect(&mut self) {
+        if self.reconnect.is_none() {
+            let callback = self.link.callback(|_| WsAction::Connect);
+            self.reconnect = Some(TimeoutService::spawn(
+                Duration::from_secs(RECONNECT_DELAY_SECS),
+                callback,
+            ));
+        }
+    }
 }
 
 impl Component for App {
@@ -256,10 +690,27 @@ impl Component for App {
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
         link.send_message(WsAction::Connect);
+
+        // closing or reloading the window would lose the draft otherwise
+        let callback = link.callback(|_| Msg::SaveDraft);
+        let onbeforeunload = Closure::wrap(Box::new(move || callback.emit(())) as Box<dyn Fn()>);
+        web_sys::window()
+            .unwrap()
+            .set_onbeforeunload(Some(onbeforeunload.as_ref().unchecked_ref()));
+        onbeforeunload.forget();
+
         App {
             link,
             model: Model::default(),
             ws: None,
+            endpoint: None,
+            next_request_id: 0,
+            pending_requests: HashMap::new(),
+            capabilities: HashSet::new(),
+            last_event_seq: None,
+            backend_run_id: None,
+            reconnect: None,
+            draft_timeout: None,
         }
     }
 
@@ -267,44 +718,119 @@ impl Component for App {
         match msg {
             Msg::WsAction(action) => match action {
                 WsAction::Connect => {
+                    self.reconnect = None;
+                    let endpoint = match self.endpoint {
+                        Some(ref endpoint) => endpoint,
+                        None => {
+                            self.link
+                                .send_future(async { Msg::Endpoint(fetch_endpoint().await) });
+                            return false;
+                        }
+                    };
+
                     let callback = self.link.callback(|Bincode(data)| Msg::WsReady(data));
                     let notification = self.link.callback(|status| match status {
                         WebSocketStatus::Opened => Msg::Connected,
                         WebSocketStatus::Closed | WebSocketStatus::Error => WsAction::Lost.into(),
                     });
-                    let task = WebSocketService::connect_binary(
-                        "ws://localhost:8081/",
-                        callback,
-                        notification,
-                    )
-                    .unwrap();
+                    let task =
+                        WebSocketService::connect_binary(endpoint, callback, notification).unwrap();
                     self.ws = Some(task);
                 }
                 WsAction::Disconnect => {
                     self.ws.take();
+                    self.pending_requests.clear();
                 }
                 WsAction::Lost => {
                     self.ws = None;
+                    self.pending_requests.clear();
+                    self.capabilities.clear();
+
+                    warn!("lost connection to the backend, reconnecting");
+                    self.schedule_reconnect();
                 }
             },
+            Msg::Endpoint(Ok(endpoint)) => {
+                self.endpoint = Some(endpoint);
+                self.link.send_message(WsAction::Connect);
+                return false;
+            }
+            Msg::Endpoint(Err(err)) => {
+                error!("can not connect to the backend, retrying: {}", err);
+                self.schedule_reconnect();
+                return false;
+            }
             Msg::Connected => {
-                let mut messages = vec![Msg::WsRequest(Request::LoadChatList {
-                    start_index: 0,
-                    stop_index: 10,
-                })];
-
-                if self.model.selected_chat.is_some() {
-                    messages.push(Msg::WsRequest(Request::LoadMessageList {
-                        start_index: 0,
-                        stop_index: 0,
+                let hello = Request::Hello {
+                    protocol_version: PROTOCOL_VERSION,
+                    capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+                };
+                if let Some(ws) = self.ws.as_mut() {
+                    self.pending_requests.insert(0, (&hello).into());
+                    ws.send_binary(Bincode(&RequestEnvelope {
+                        request_id: 0,
+                        request: hello,
                     }));
                 }
-
-                self.link.send_message_batch(messages);
                 return false;
             }
             Msg::WsReady(response) => match response {
                 Ok(data) => match data {
+                    Response::Welcome {
+                        refusal: Some(refusal),
+                        ..
+                    } => {
+                        // the backend closes the connection, the refusal is shown once while
+                        // reconnecting until it is updated
+                        warn!("backend refused the connection: {}", refusal);
+                        let request = self.pending_requests.remove(&0).unwrap_or("Hello");
+                        let mut failures = self.model.request_failures.clone_inner();
+                        if failures.iter().any(|failure| failure.message == refusal) {
+                            return false;
+                        }
+                        failures.push(RequestFailure {
+                            request,
+                            kind: ErrorKind::IncompatibleProtocol,
+                            message: refusal,
+                        });
+                        self.model.request_failures.neq_assign(failures);
+                        return true;
+                    }
+                    Response::Welcome {
+                        protocol_version,
+                        capabilities,
+                        run_id,
+                        refusal: None,
+                    } => {
+                        info!(
+                            "connected with protocol {}, capabilities {:?}",
+                            protocol_version, capabilities
+                        );
+                        self.pending_requests.remove(&0);
+                        self.capabilities = capabilities.into_iter().collect();
+
+                        let mut messages = Vec::new();
+
+                        // after reconnecting, select what was shown before in the new session
+                        if let Some(account) = *self.model.selected_account {
+                            messages.push(Msg::WsRequest(Request::SelectAccount { account }));
+                            if let Some(chat_id) = *self.model.selected_chat_id {
+                                messages
+                                    .push(Msg::WsRequest(Request::SelectChat { account, chat_id }));
+                            }
+                        }
+                        messages.push(Msg::LoadChatList(0, 10));
+                        // the backend resyncs if it was restarted in the meantime
+                        let previous_run_id = self.backend_run_id.replace(run_id);
+                        if let (Some(run_id), Some(last_seq)) =
+                            (previous_run_id, self.last_event_seq)
+                        {
+                            messages.push(Msg::WsRequest(Request::Resume { run_id, last_seq }));
+                        }
+
+                        self.link.send_message_batch(messages);
+                        return false;
+                    }
                     Response::MessageList {
                         chat_id: _,
                         range,
@@ -317,9 +843,15 @@ impl Component for App {
 
                         return true;
                     }
-                    Response::ChatList { range, len, chats } => {
+                    Response::ChatList {
+                        range,
+                        len,
+                        archived_len,
+                        chats,
+                    } => {
                         self.model.chats_range.neq_assign(range);
                         self.model.chats_len.neq_assign(len);
+                        self.model.archived_len.neq_assign(archived_len);
                         info!("ChatList {:?}", chats);
                         self.model.chats.neq_assign(chats);
                         return true;
@@ -329,14 +861,9 @@ impl Component for App {
                         chat,
                         chat_id,
                     } => {
-                        self.model.selected_account.neq_assign(Some(account));
-                        self.model.selected_chat.neq_assign(chat);
-                        self.model.selected_chat_id.neq_assign(chat_id);
+                        self.change_chat(Some(account), chat_id, chat);
 
-                        let message = Msg::WsRequest(Request::LoadChatList {
-                            start_index: 0,
-                            stop_index: 10,
-                        });
+                        let message = Msg::LoadChatList(0, 10);
                         self.link.send_message(message);
                         return true;
                     }
@@ -344,33 +871,46 @@ impl Component for App {
                         info!("RemoteUpdate {:?}", state);
                         self.model.accounts.neq_assign(state.shared.accounts);
                         self.model.errors.neq_assign(state.shared.errors);
-                        self.model
-                            .selected_account
-                            .neq_assign(state.shared.selected_account);
-                        self.model
-                            .selected_chat_id
-                            .neq_assign(state.shared.selected_chat_id);
-                        self.model
-                            .selected_chat
-                            .neq_assign(state.shared.selected_chat);
+                        self.change_chat(
+                            state.shared.selected_account,
+                            state.shared.selected_chat_id,
+                            state.shared.selected_chat,
+                        );
                         return true;
                     }
-                    Response::Event { account, event } => {
+                    Response::Event {
+                        seq,
+                        account,
+                        event,
+                    } => {
+                        // replayed events arrive after newer live ones
+                        self.last_event_seq = self.last_event_seq.max(Some(seq));
+
                         match event {
                             Event::MessagesChanged { chat_id } => {
                                 info!("changed {}", chat_id);
                                 // refresh chat list
-                                let mut messages = vec![Msg::WsRequest(Request::LoadChatList {
-                                    start_index: self.model.chats_range.0,
-                                    stop_index: self.model.chats_range.1,
-                                })];
+                                let mut messages = vec![Msg::LoadChatList(
+                                    self.model.chats_range.0,
+                                    self.model.chats_range.1,
+                                )];
 
                                 if *self.model.selected_chat_id.as_ref() == Some(chat_id) {
-                                    // if the selected chat changed, refresh that
+                                    // if the selected chat changed, refresh that, including
+                                    // new messages if the end is shown, e.g. of muted chats
+                                    let (start_index, mut stop_index) = *self.model.messages_range;
+                                    if stop_index >= self.model.message_items.len() {
+                                        stop_index += 1;
+                                    }
                                     messages.push(Msg::WsRequest(Request::LoadMessageList {
-                                        start_index: self.model.messages_range.0,
-                                        stop_index: self.model.messages_range.1,
+                                        start_index,
+                                        stop_index,
                                     }));
+
+                                    // e.g. members were added or removed
+                                    if *self.model.show_chat_info {
+                                        self.load_chat_members();
+                                    }
                                 }
 
                                 self.link.send_message_batch(messages);
@@ -392,10 +932,10 @@ impl Component for App {
                                 notification.set_onclick(Some(onclick.as_ref().unchecked_ref()));
 
                                 // refresh chat list
-                                let mut messages = vec![Msg::WsRequest(Request::LoadChatList {
-                                    start_index: self.model.chats_range.0,
-                                    stop_index: self.model.chats_range.1,
-                                })];
+                                let mut messages = vec![Msg::LoadChatList(
+                                    self.model.chats_range.0,
+                                    self.model.chats_range.1,
+                                )];
 
                                 if *self.model.selected_chat_id.as_ref() == Some(chat_id) {
                                     // if the selected chat changed, refresh that
@@ -407,6 +947,41 @@ impl Component for App {
 
                                 self.link.send_message_batch(messages);
                             }
+                            Event::ContactsChanged { contact_id }
+                                if *self.model.selected_account == Some(account) =>
+                            {
+                                info!("contacts changed {:?}", contact_id);
+                                // names and avatars show up in the chat list and messages
+                                let mut messages = vec![Msg::LoadChatList(
+                                    self.model.chats_range.0,
+                                    self.model.chats_range.1,
+                                )];
+                                if self.model.selected_chat_id.is_some() {
+                                    messages.push(Msg::WsRequest(Request::LoadMessageList {
+                                        start_index: self.model.messages_range.0,
+                                        stop_index: self.model.messages_range.1,
+                                    }));
+                                }
+
+                                let shown =
+                                    self.model.contact_detail.as_ref().as_ref().map(|c| c.id);
+                                if let Some(shown) = shown {
+                                    if contact_id.map_or(true, |id| id == shown) {
+                                        messages.push(Msg::ShowContact(shown));
+                                    }
+                                }
+
+                                if *self.model.show_chat_info {
+                                    self.load_chat_members();
+                                }
+                                // e.g. a contact was blocked or unblocked
+                                if self.model.left_panel == LeftPanel::Settings {
+                                    self.load_blocked_contacts();
+                                }
+
+                                self.model.contacts.neq_assign(None);
+                                self.link.send_message_batch(messages);
+                            }
                             Event::Log(log) => match log {
                                 shared::Log::Info(_msg) => {
                                     // info!("[{}]: {:?}", account, msg);
@@ -426,14 +1001,91 @@ impl Component for App {
                         self.model.contacts = Mrc::new(Some(contacts));
                         return true;
                     }
+                    Response::Ack { request_id } => {
+                        self.pending_requests.remove(&request_id);
+                    }
+                    Response::Error {
+                        request_id,
+                        kind,
+                        message,
+                    } => {
+                        let request = request_id
+                            .and_then(|id| self.pending_requests.remove(&id))
+                            .unwrap_or("Request");
+                        warn!("{} failed ({:?}): {}", request, kind, message);
+
+                        let mut failures = self.model.request_failures.clone_inner();
+                        failures.push(RequestFailure {
+                            request,
+                            kind,
+                            message,
+                        });
+                        self.model.request_failures.neq_assign(failures);
+                        return true;
+                    }
+                    Response::MessageInfo(info) => {
+                        self.model.contact_detail.neq_assign(None);
+                        self.model.show_chat_info.neq_assign(false);
+                        self.model.message_info.neq_assign(Some(info));
+                        return true;
+                    }
+                    Response::BlockedContacts { account, contacts } => {
+                        if *self.model.selected_account != Some(account) {
+                            return false;
+                        }
+                        return self.model.blocked_contacts.neq_assign(Some(contacts));
+                    }
+                    Response::ChatMembers { chat_id, members } => {
+                        if *self.model.selected_chat_id != Some(chat_id) {
+                            return false;
+                        }
+                        return self.model.chat_members.neq_assign(Some(members));
+                    }
+                    Response::ContactDetail(contact) => {
+                        self.model.message_info.neq_assign(None);
+                        self.model.show_chat_info.neq_assign(false);
+                        self.model.contact_detail.neq_assign(Some(contact));
+                        return true;
+                    }
+                    Response::SearchResults { query, results } => {
+                        return self.model.search_results.neq_assign(Some((query, results)));
+                    }
+                    Response::Resync => {
+                        info!("missed events, reloading");
+                        // continue with the events of the current run
+                        self.last_event_seq = None;
+                        let mut messages = vec![Msg::LoadChatList(
+                            self.model.chats_range.0,
+                            self.model.chats_range.1,
+                        )];
+
+                        if self.model.selected_chat_id.is_some() {
+                            messages.push(Msg::WsRequest(Request::LoadMessageList {
+                                start_index: self.model.messages_range.0,
+                                stop_index: self.model.messages_range.1,
+                            }));
+                        }
+
+                        self.link.send_message_batch(messages);
+                    }
                 },
                 Err(err) => {
                     warn!("{:#?}", err);
                 }
             },
-            Msg::WsRequest(req) => {
+            Msg::WsRequest(request) => {
                 if let Some(ws) = self.ws.as_mut() {
-                    ws.send_binary(Bincode(&req));
+                    self.next_request_id = self.next_request_id.wrapping_add(1).max(1);
+                    let request_id = self.next_request_id;
+                    // without acks there is no way to tell when to forget a request
+                    if self.capabilities.contains(capability::ACK) {
+                        self.pending_requests.insert(request_id, (&request).into());
+                    }
+
+                    ws.send_binary(Bincode(&RequestEnvelope {
+                        request_id,
+                        request,
+                    }));
                 }
             }
             Msg::ShowAccountCreation => {
@@ -462,6 +1114,182 @@ impl Component for App {
                 }
                 return true;
             }
+            Msg::DismissRequestFailure(index) => {
+                let mut failures = self.model.request_failures.clone_inner();
+                if index < failures.len() {
+                    failures.remove(index);
+                }
+                return self.model.request_failures.neq_assign(failures);
+            }
+            Msg::RetryError(error) => {
+                match error.kind {
+                    ErrorKind::Login | ErrorKind::Import => {
+                        self.model.show_account_creation = true;
+                    }
+                    _ => {}
+                }
+                self.link
+                    .send_message(Msg::WsRequest(Request::DismissError { id: error.id }));
+                return true;
+            }
+            Msg::SendMessage(text) => {
+                let file = self.model.draft_file.clone_inner();
+                let request = match (&*self.model.reply_to, file) {
+                    (Some(quote), file) => Request::SendReply {
+                        quote_msg_id: quote.id,
+                        text: Some(text),
+                        file,
+                    },
+                    (None, Some(file)) => Request::SendFileMessage {
+                        typ: file.typ,
+                        path: file.path,
+                        text: Some(text),
+                        mime: file.mime,
+                    },
+                    (None, None) => Request::SendTextMessage { text },
+                };
+                self.link.send_message(Msg::WsRequest(request));
+
+                self.model.draft_text.neq_assign(String::new());
+                self.model.draft_file.neq_assign(None);
+                self.model.reply_to.neq_assign(None);
+                // sending does not remove the draft from the backend
+                self.draft_timeout = None;
+                if let (Some(account), Some(chat_id)) =
+                    (*self.model.selected_account, *self.model.selected_chat_id)
+                {
+                    self.save_draft(account, chat_id);
+                }
+                return true;
+            }
+            Msg::Reply(message) => {
+                self.schedule_draft_save();
+                return self.model.reply_to.neq_assign(message);
+            }
+            Msg::DraftInput(text) => {
+                // the input shows the text already
+                self.model.draft_text.neq_assign(text);
+                self.schedule_draft_save();
+            }
+            Msg::RemoveDraftFile => {
+                self.schedule_draft_save();
+                return self.model.draft_file.neq_assign(None);
+            }
+            Msg::SaveDraft => {
+                self.draft_timeout = None;
+                if let (Some(account), Some(chat_id)) =
+                    (*self.model.selected_account, *self.model.selected_chat_id)
+                {
+                    self.save_draft(account, chat_id);
+                }
+            }
+            Msg::LoadChatList(start_index, stop_index) => {
+                let query = Some(self.model.chat_list_query.clone_inner())
+                    .filter(|query| !query.trim().is_empty());
+                self.link
+                    .send_message(Msg::WsRequest(Request::LoadChatList {
+                        start_index,
+                        stop_index,
+                        query,
+                        flags: *self.model.chat_list_flags,
+                    }));
+            }
+            Msg::FilterChats(query) => {
+                // the filter box shows the query already
+                if self.model.chat_list_query.neq_assign(query) {
+                    self.link.send_message(Msg::LoadChatList(0, 10));
+                }
+            }
+            Msg::ChangeChatListFlags(flags) => {
+                if self.model.chat_list_flags.neq_assign(flags) {
+                    self.link.send_message(Msg::LoadChatList(0, 10));
+                    return true;
+                }
+            }
+            Msg::ShowArchived(archived_only) => {
+                let flags = ChatListFlags {
+                    archived_only,
+                    ..*self.model.chat_list_flags
+                };
+                let panel = if archived_only {
+                    LeftPanel::Archived
+                } else {
+                    LeftPanel::Chats
+                };
+                self.link.send_message_batch(vec![
+                    Msg::ChangeChatListFlags(flags),
+                    Msg::ChangePanel(ChangePanel::Left(panel)),
+                ]);
+            }
+            Msg::Search(query, chat_id) => {
+                if let Some(account) = *self.model.selected_account {
+                    self.link
+                        .send_message(Msg::WsRequest(Request::SearchMessages {
+                            account,
+                            chat_id,
+                            query,
+                        }));
+                }
+            }
+            Msg::CloseMessageInfo => {
+                return self.model.message_info.neq_assign(None);
+            }
+            Msg::ShowContact(contact_id) => {
+                if let Some(account) = *self.model.selected_account {
+                    self.link
+                        .send_message(Msg::WsRequest(Request::GetContactDetail {
+                            account,
+                            contact_id,
+                        }));
+                }
+            }
+            Msg::CloseContact => {
+                return self.model.contact_detail.neq_assign(None);
+            }
+            Msg::ShowChatInfo => {
+                self.model.message_info.neq_assign(None);
+                self.model.contact_detail.neq_assign(None);
+                self.load_chat_members();
+                return self.model.show_chat_info.neq_assign(true);
+            }
+            Msg::CloseChatInfo => {
+                return self.model.show_chat_info.neq_assign(false);
+            }
+            Msg::ShowSettings => {
+                self.model.blocked_contacts.neq_assign(None);
+                self.load_blocked_contacts();
+                self.link
+                    .send_message(Msg::ChangePanel(ChangePanel::Left(LeftPanel::Settings)));
+            }
+            Msg::CloseSearch => {
+                self.model.search_results.neq_assign(None);
+                self.link
+                    .send_message(Msg::ChangePanel(ChangePanel::Left(LeftPanel::Chats)));
+            }
+            Msg::Forward(msg_ids) => {
+                self.model.forward_msg_ids.neq_assign(msg_ids);
+                self.link
+                    .send_message(Msg::ChangePanel(ChangePanel::Left(LeftPanel::Forward)));
+            }
+            Msg::ForwardTo(target_chat_id) => {
+                if let Some(account) = *self.model.selected_account {
+                    let msg_ids = std::mem::take(&mut *self.model.forward_msg_ids);
+                    self.link.send_message_batch(vec![
+                        Msg::WsRequest(Request::ForwardMessages {
+                            account,
+                            msg_ids,
+                            target_chat_id,
+                        }),
+                        // show where the messages went
+                        Msg::WsRequest(Request::SelectChat {
+                            account,
+                            chat_id: target_chat_id,
+                        }),
+                    ]);
+                }
+                self.link
+                    .send_message(Msg::ChangePanel(ChangePanel::Left(LeftPanel::Chats)));
+            }
         }
         false
     }
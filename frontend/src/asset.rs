@@ -0,0 +1,24 @@
+//! URLs for files of the backend, like avatars and attachments.
+
+use std::cell::RefCell;
+use std::path::Path;
+
+thread_local! {
+    /// Set when running in a browser, where files are fetched from the `dc40-server`.
+    static TOKEN: RefCell<Option<String>> = RefCell::new(None);
+}
+
+pub fn set_token(token: String) {
+    TOKEN.with(|t| *t.borrow_mut() = Some(token));
+}
+
+pub fn url(path: &Path) -> String {
+    TOKEN.with(|token| match *token.borrow() {
+        Some(ref token) => format!(
+            "asset?path={}&token={}",
+            js_sys::encode_uri_component(&path.to_string_lossy()),
+            token
+        ),
+        None => format!("asset://{}", path.to_string_lossy()),
+    })
+}
@@ -1,7 +1,10 @@
 use crate::components::{message_input::MessageInput, messages::Messages};
-use shared::ChatState;
+use shared::{Attachment, ChatState, InnerChatMessage};
 use yew::prelude::*;
-use yewtil::{ptr::Mrc, NeqAssign};
+use yewtil::{
+    ptr::{Irc, Mrc},
+    NeqAssign,
+};
 
 use super::messages::Props as MessagesProps;
 
@@ -12,6 +15,17 @@ pub struct Props {
     pub send_message: Callback<String>,
     pub messages_props: MessagesProps,
     pub selected_chat: Mrc<Option<ChatState>>,
+    pub reply_to: Irc<Option<InnerChatMessage>>,
+    pub cancel_reply_callback: Callback<()>,
+    pub draft_text: Irc<String>,
+    pub draft_input_callback: Callback<String>,
+    pub draft_file: Irc<Option<Attachment>>,
+    pub cancel_file_callback: Callback<()>,
+    /// Sets the disappearing messages timer of the chat, in seconds.
+    pub set_ephemeral_timer_callback: Callback<u32>,
+    /// Shows the contact with the given id.
+    pub contact_callback: Callback<u32>,
+    pub chat_info_callback: Callback<()>,
 }
 
 pub struct Chat {
@@ -60,21 +74,41 @@ impl Component for Chat {
             }
         } else {
             html! {
-                <MessageInput send_callback=self.props.send_message.clone() />
+                <>
+                    { self.view_reply_preview() }
+                    { self.view_file_preview() }
+                    <MessageInput
+                        send_callback=self.props.send_message.clone()
+                        text=self.props.draft_text.clone()
+                        input_callback=self.props.draft_input_callback.clone() />
+                </>
             }
         };
 
         let (title, subtitle) = get_titles(&chat);
+        let onclick_titles = match chat.contact_id {
+            Some(contact_id) if !chat.is_self_talk && !chat.is_device_talk => {
+                let cb = self.props.contact_callback.clone();
+                Some(Callback::from(move |_| cb.emit(contact_id)))
+            }
+            _ => None,
+        };
+        let titles_class = classes!(
+            "chat-header-titles",
+            onclick_titles.is_some().then(|| "clickable")
+        );
 
         html! {
             <div class="chat">
                 <div class="chat-header">
-                    <div>
+                    <div class=titles_class onclick=onclick_titles>
                         <div class="chat-header-name">{title}</div>
                         <div class="chat-header-subtitle">
                         { subtitle }
                         </div>
                     </div>
+                    { self.view_ephemeral_timer(chat) }
+                    { self.view_chat_info_button(chat) }
                 </div>
 
                 <Messages with self.props.messages_props.clone() />
@@ -85,7 +119,127 @@ impl Component for Chat {
     }
 }
 
+/// Timer choices for disappearing messages, in seconds.
+const EPHEMERAL_TIMERS: &[(u32, &str)] = &[
+    (0, "Off"),
+    (5 * 60, "5 minutes"),
+    (60 * 60, "1 hour"),
+    (24 * 60 * 60, "1 day"),
+    (7 * 24 * 60 * 60, "1 week"),
+    (4 * 7 * 24 * 60 * 60, "4 weeks"),
+];
+
+impl Chat {
+    fn view_chat_info_button(&self, chat: &ChatState) -> Html {
+        if !is_group(chat) {
+            return html! {};
+        }
+
+        let cb = self.props.chat_info_callback.clone();
+        let onclick: Callback<_> = (move |_| cb.emit(())).into();
+        html! {
+            <div class="chat-header-info icon information" title="Group info" onclick=onclick></div>
+        }
+    }
+
+    fn view_ephemeral_timer(&self, chat: &ChatState) -> Html {
+        if chat.is_contact_request {
+            return html! {};
+        }
+
+        let cb = self.props.set_ephemeral_timer_callback.clone();
+        let onchange: Callback<_> = (move |e: ChangeData| {
+            if let ChangeData::Select(select) = e {
+                if let Ok(timer) = select.value().parse() {
+                    cb.emit(timer);
+                }
+            }
+        })
+        .into();
+
+        let custom = if EPHEMERAL_TIMERS
+            .iter()
+            .any(|(timer, _)| *timer == chat.ephemeral_timer)
+        {
+            html! {}
+        } else {
+            html! {
+                <option value=chat.ephemeral_timer.to_string() selected=true>
+                    { format!("{} seconds", chat.ephemeral_timer) }
+                </option>
+            }
+        };
+
+        html! {
+            <div class="chat-header-timer" title="Disappearing messages">
+                <div class="icon timer"></div>
+                <select onchange=onchange>
+                    { for EPHEMERAL_TIMERS.iter().map(|(timer, label)| html! {
+                        <option value=timer.to_string() selected=*timer == chat.ephemeral_timer>
+                            { label }
+                        </option>
+                    }) }
+                    { custom }
+                </select>
+            </div>
+        }
+    }
+
+    fn view_reply_preview(&self) -> Html {
+        let quote = match &*self.props.reply_to {
+            Some(quote) => quote,
+            None => return html! {},
+        };
+
+        let cb = self.props.cancel_reply_callback.clone();
+        let cancel: Callback<_> = (move |_| cb.emit(())).into();
+        let text = quote
+            .text
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", quote.viewtype));
+
+        html! {
+            <div class="reply-preview">
+                <div class="reply-preview-quote">
+                    <div class="reply-preview-sender">{ &quote.from_first_name }</div>
+                    <div class="reply-preview-text">{ text }</div>
+                </div>
+                <div class="icon close small" title="Cancel reply" onclick=cancel></div>
+            </div>
+        }
+    }
+
+    fn view_file_preview(&self) -> Html {
+        let file = match &*self.props.draft_file {
+            Some(file) => file,
+            None => return html! {},
+        };
+
+        let cb = self.props.cancel_file_callback.clone();
+        let cancel: Callback<_> = (move |_| cb.emit(())).into();
+        let name = file
+            .path
+            .rsplit(&['/', '\\'][..])
+            .next()
+            .unwrap_or_default();
+
+        html! {
+            <div class="reply-preview">
+                <div class="reply-preview-quote">
+                    <div class="reply-preview-sender">{ format!("{:?}", file.typ) }</div>
+                    <div class="reply-preview-text">{ name }</div>
+                </div>
+                <div class="icon close small" title="Remove attachment" onclick=cancel></div>
+            </div>
+        }
+    }
+}
+
 /// Get the title and subtitle texts.
+fn is_group(chat: &ChatState) -> bool {
+    chat.chat_type == "Group" || chat.chat_type == "VerifiedGroup"
+}
+
 fn get_titles(chat: &ChatState) -> (String, String) {
     if chat.id == 1 {
         // deaddrop
@@ -98,7 +252,7 @@ fn get_titles(chat: &ChatState) -> (String, String) {
 
         let subtitle = if chat.is_contact_request {
             "Contact Request".to_string()
-        } else if chat.chat_type == "Group" || chat.chat_type == "VerifiedGroup" {
+        } else if is_group(chat) {
             if chat.member_count == 1 {
                 "1 member".to_string()
             } else {
@@ -0,0 +1,324 @@
+use shared::{ChatState, ContactInfo, CONTACT_ID_SELF};
+use wasm_bindgen::prelude::*;
+use yew::prelude::*;
+use yewtil::{future::LinkFuture, ptr::Irc, NeqAssign};
+
+use crate::asset;
+
+#[wasm_bindgen(module = "/src/js/tauri_wrapper.js")]
+extern "C" {
+    async fn invoke_pick_image() -> JsValue;
+    fn is_tauri() -> bool;
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub chat: ChatState,
+    pub members: Irc<Option<Vec<ContactInfo>>>,
+    pub contacts: Irc<Option<Vec<ContactInfo>>>,
+    pub load_contacts_callback: Callback<()>,
+    pub rename_callback: Callback<String>,
+    /// Sets the group image to the file at the path, `None` removes it.
+    pub set_image_callback: Callback<Option<String>>,
+    pub add_member_callback: Callback<u32>,
+    pub remove_member_callback: Callback<u32>,
+    pub show_contact_callback: Callback<u32>,
+    /// Opens the 1:1 chat with the member.
+    pub message_privately_callback: Callback<u32>,
+    pub leave_callback: Callback<()>,
+    pub close_callback: Callback<()>,
+}
+
+pub struct ChatInfoPanel {
+    link: ComponentLink<Self>,
+    props: Props,
+    /// Whether the members are listed for removal and the contacts to add.
+    adding: bool,
+}
+
+pub enum Msg {
+    PickImage,
+    ImagePicked(Option<String>),
+    ToggleAdding,
+}
+
+impl Component for ChatInfoPanel {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        ChatInfoPanel {
+            link,
+            props,
+            adding: false,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::PickImage => {
+                self.link.send_future(async {
+                    let path = unsafe { invoke_pick_image().await };
+                    Msg::ImagePicked(path.as_string())
+                });
+                false
+            }
+            Msg::ImagePicked(path) => {
+                if path.is_some() {
+                    self.props.set_image_callback.emit(path);
+                }
+                false
+            }
+            Msg::ToggleAdding => {
+                self.adding = !self.adding;
+                if self.adding && self.props.contacts.is_none() {
+                    self.props.load_contacts_callback.emit(());
+                }
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.chat.id != props.chat.id {
+            self.adding = false;
+        }
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        let chat = &self.props.chat;
+        let cb = self.props.close_callback.clone();
+        let close_cb: Callback<_> = (move |_| cb.emit(())).into();
+
+        let cb = self.props.rename_callback.clone();
+        let name = chat.name.clone();
+        let onrename: Callback<_> = (move |e: ChangeData| {
+            if let ChangeData::Value(new_name) = e {
+                if !new_name.trim().is_empty() && new_name.trim() != name {
+                    cb.emit(new_name);
+                }
+            }
+        })
+        .into();
+
+        let cb = self.props.leave_callback.clone();
+        let onleave: Callback<_> = (move |_| cb.emit(())).into();
+
+        // members that left can't change the group anymore
+        let actions = if chat.can_send {
+            let ontoggle = self.link.callback(|_| Msg::ToggleAdding);
+            html! {
+                <div class="chat-info-actions">
+                    <button onclick=ontoggle>
+                        { if self.adding { "Done" } else { "Edit members" } }
+                    </button>
+                    <button class="danger" onclick=onleave>{"Leave group"}</button>
+                </div>
+            }
+        } else {
+            html! {
+                <p class="text-center">{"You are not a member of this group"}</p>
+            }
+        };
+
+        html! {
+            <div class="message-info-panel contact-panel chat-info-panel">
+                <div class="message-info-header">
+                    <div class="message-info-title">{"Group"}</div>
+                    <div class="icon close" title="Close" onclick=close_cb></div>
+                </div>
+                <div class="contact-summary">
+                    { self.view_image() }
+                    <input
+                        class="contact-name"
+                        type="text"
+                        title="Rename group"
+                        disabled=!chat.can_send
+                        value=chat.name.clone()
+                        onchange=onrename />
+                </div>
+                { actions }
+                { self.view_contacts() }
+                { self.view_members() }
+            </div>
+        }
+    }
+}
+
+impl ChatInfoPanel {
+    fn view_image(&self) -> Html {
+        let chat = &self.props.chat;
+        let image = if let Some(ref profile_image) = chat.profile_image {
+            html! {
+                <img class="contact-image" src={asset::url(profile_image)} alt="group avatar" />
+            }
+        } else {
+            let image_style = format!("background-color: #{:06X}", chat.color);
+            html! {
+                <div class="contact-image letter-icon" style={image_style}>
+                    {chat.name.chars().next().unwrap_or_default()}
+                </div>
+            }
+        };
+
+        // picking files needs the native dialog
+        let buttons = if chat.can_send && is_tauri() {
+            let onpick = self.link.callback(|_| Msg::PickImage);
+            let remove = if chat.profile_image.is_some() {
+                let cb = self.props.set_image_callback.clone();
+                let onremove: Callback<_> = (move |_| cb.emit(None)).into();
+                html! {
+                    <button onclick=onremove>{"Remove image"}</button>
+                }
+            } else {
+                html! {}
+            };
+            html! {
+                <div class="chat-info-image-buttons">
+                    <button onclick=onpick>{"Change image"}</button>
+                    { remove }
+                </div>
+            }
+        } else {
+            html! {}
+        };
+
+        html! {
+            <>
+                { image }
+                { buttons }
+            </>
+        }
+    }
+
+    fn view_members(&self) -> Html {
+        let members = match &*self.props.members {
+            Some(members) => members,
+            None => {
+                return html! {
+                    <p class="text-center">{"Loading members"}</p>
+                }
+            }
+        };
+
+        html! {
+            <div class="chat-info-members">
+                <div class="chat-info-section">
+                    { format!("{} members", members.len()) }
+                </div>
+                { for members.iter().map(|member| self.view_member(member)) }
+            </div>
+        }
+    }
+
+    fn view_member(&self, member: &ContactInfo) -> Html {
+        let contact_id = member.id;
+        let cb = self.props.show_contact_callback.clone();
+        let onshow: Callback<_> = (move |_| cb.emit(contact_id)).into();
+
+        let image = if let Some(ref profile_image) = member.profile_image {
+            html! {
+                <img class="image-icon" src={asset::url(profile_image)} alt="member avatar" />
+            }
+        } else {
+            let image_style = format!("background-color: #{:06X}", member.color);
+            html! {
+                <div class="letter-icon" style={image_style}>
+                    {member.display_name.chars().next().unwrap_or_default()}
+                </div>
+            }
+        };
+        let verified = if member.is_verified {
+            html! { <div class="icon checkmark small" title="Verified"></div> }
+        } else {
+            html! {}
+        };
+
+        let actions = if contact_id == CONTACT_ID_SELF {
+            html! {}
+        } else {
+            let cb = self.props.message_privately_callback.clone();
+            let onmessage: Callback<_> = (move |_| cb.emit(contact_id)).into();
+            html! {
+                <div class="chat-info-member-actions">
+                    <button onclick=onmessage>{"Message privately"}</button>
+                </div>
+            }
+        };
+
+        html! {
+            <div key=member.id class="chat-info-member">
+                <div class="chat-info-member-icon" title="Show contact" onclick=onshow.clone()>
+                    { image }
+                </div>
+                <div class="chat-info-member-body">
+                    <div class="chat-info-contact-name" onclick=onshow>
+                        { &member.display_name }
+                        { verified }
+                    </div>
+                    <div class="chat-info-contact-mail">{ &member.mail }</div>
+                    { actions }
+                </div>
+            </div>
+        }
+    }
+
+    fn view_contacts(&self) -> Html {
+        if !self.adding {
+            return html! {};
+        }
+
+        let is_member = |contact: &ContactInfo| match &*self.props.members {
+            Some(members) => members.iter().any(|member| member.id == contact.id),
+            None => false,
+        };
+
+        // the own contact leaves with "Leave group"
+        let removable = match &*self.props.members {
+            Some(members) => html! {
+                { for members.iter().filter(|member| member.id != CONTACT_ID_SELF).map(|member| {
+                    let cb = self.props.remove_member_callback.clone();
+                    let contact_id = member.id;
+                    let onremove: Callback<_> = (move |_| cb.emit(contact_id)).into();
+                    html! {
+                        <div key=member.id class="chat-info-contact member">
+                            <div class="chat-info-contact-name">{ &member.display_name }</div>
+                            <div class="chat-info-contact-mail">{ &member.mail }</div>
+                            <button class="danger" onclick=onremove>{"Remove"}</button>
+                        </div>
+                    }
+                }) }
+            },
+            None => html! {},
+        };
+
+        let addable = match &*self.props.contacts {
+            Some(contacts) => html! {
+                <>
+                    { for contacts.iter().filter(|contact| !is_member(contact)).map(|contact| {
+                        let cb = self.props.add_member_callback.clone();
+                        let contact_id = contact.id;
+                        let onclick: Callback<_> = (move |_| cb.emit(contact_id)).into();
+                        html! {
+                            <div key=contact.id class="chat-info-contact" title="Add to group" onclick=onclick>
+                                <div class="chat-info-contact-name">{ &contact.display_name }</div>
+                                <div class="chat-info-contact-mail">{ &contact.mail }</div>
+                            </div>
+                        }
+                    }) }
+                </>
+            },
+            None => html! {
+                <p class="text-center">{"Loading contacts"}</p>
+            },
+        };
+
+        html! {
+            <div class="chat-info-contacts">
+                { removable }
+                { addable }
+            </div>
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::rc::Rc;
+
+use shared::ChatState;
+use yew::prelude::*;
+use yewtil::{ptr::Irc, NeqAssign};
+
+use super::list::List;
+
+/// Lets the user pick a chat, e.g. to forward messages to.
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub title: String,
+    pub chats: Irc<Vec<ChatState>>,
+    pub chats_range: Irc<(usize, usize)>,
+    pub chats_len: Irc<usize>,
+    pub fetch_callback: Callback<(usize, usize)>,
+    pub pick_callback: Callback<u32>,
+    pub close_callback: Callback<()>,
+}
+
+pub struct ChatPicker {
+    props: Props,
+}
+
+impl Component for ChatPicker {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        ChatPicker { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        let pick_cb = self.props.pick_callback.clone();
+        let render_element: Rc<dyn Fn(ChatState) -> Html> =
+            Rc::new(move |chat: ChatState| -> Html {
+                let cb = pick_cb.clone();
+                let chat_id = chat.id;
+                let onclick: Callback<_> = (move |_| cb.emit(chat_id)).into();
+
+                html! {
+                    <div key=chat.id onclick=onclick class="contact">
+                        <h2>{&chat.name}</h2>
+                        <p>{&chat.preview}</p>
+                    </div>
+                }
+            });
+
+        let cb = self.props.close_callback.clone();
+        let close_cb: Callback<_> = (move |_| cb.emit(())).into();
+
+        html! {
+            <div class="create-chat">
+                <div class="search">
+                    <button id="close" onclick=close_cb> <div class="icon arrow-back" /> </button>
+                    <div class="picker-title">{&self.props.title}</div>
+                </div>
+
+                <List<ChatState>
+                    class="contact-list".to_string()
+                    list=self.props.chats.clone()
+                    list_range=self.props.chats_range.clone()
+                    list_len=self.props.chats_len.clone()
+                    selected_id=Irc::new(None)
+                    fetch_callback=self.props.fetch_callback.clone()
+                    render_element=render_element
+                    auto_scroll=false
+                    batch_size=10 />
+            </div>
+        }
+    }
+}
@@ -1,11 +1,17 @@
-use shared::{ChatState, SharedAccountState};
+use shared::{ChatListFlags, ChatState, MuteDuration, SharedAccountState};
 use std::collections::HashMap;
 use std::rc::Rc;
-use yew::{html, Callback, Component, ComponentLink, Html, Properties, ShouldRender};
+use std::time::Duration;
+use yew::{
+    html,
+    services::{timeout::TimeoutTask, TimeoutService},
+    Callback, Component, ComponentLink, Html, InputData, Properties, ShouldRender,
+};
 use yewtil::{ptr::Irc, NeqAssign};
 
 use super::context_menu::ContextMenu;
 use super::list::List;
+use crate::asset;
 
 #[derive(Properties, Clone, PartialEq)]
 pub struct Props {
@@ -19,30 +25,75 @@ pub struct Props {
     pub unpin_chat_callback: Callback<(u32, u32)>,
     pub archive_chat_callback: Callback<(u32, u32)>,
     pub unarchive_chat_callback: Callback<(u32, u32)>,
+    pub mute_chat_callback: Callback<(u32, u32, MuteDuration)>,
     pub chats: Irc<Vec<ChatState>>,
     pub chats_range: Irc<(usize, usize)>,
     pub chats_len: Irc<usize>,
     pub fetch_callback: Callback<(usize, usize)>,
     pub create_chat_callback: Callback<()>,
+    pub search_callback: Callback<()>,
+    pub chat_list_query: Irc<String>,
+    pub chat_list_flags: Irc<ChatListFlags>,
+    pub filter_callback: Callback<String>,
+    pub flags_callback: Callback<ChatListFlags>,
+    pub archived_len: Irc<usize>,
+    /// Switches between the archived and the other chats.
+    pub show_archived_callback: Callback<bool>,
 }
 
+/// How long typing has to pause before the chat list is filtered.
+const FILTER_DELAY_MS: u64 = 300;
+
 pub struct Chatlist {
+    link: ComponentLink<Self>,
     props: Props,
+    /// The query in the filter box, ahead of `chat_list_query` while typing.
+    query: String,
+    filter_timeout: Option<TimeoutTask>,
+}
+
+pub enum Msg {
+    FilterInput(String),
+    Filter,
 }
 
 impl Component for Chatlist {
-    type Message = ();
+    type Message = Msg;
     type Properties = Props;
 
-    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
-        Chatlist { props }
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Chatlist {
+            link,
+            query: props.chat_list_query.to_string(),
+            props,
+            filter_timeout: None,
+        }
     }
 
-    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
-        true
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::FilterInput(query) => {
+                // replacing the task cancels the previous one
+                self.query = query;
+                let callback = self.link.callback(|_| Msg::Filter);
+                self.filter_timeout = Some(TimeoutService::spawn(
+                    Duration::from_millis(FILTER_DELAY_MS),
+                    callback,
+                ));
+            }
+            Msg::Filter => {
+                self.filter_timeout = None;
+                self.props.filter_callback.emit(self.query.clone());
+            }
+        }
+        // the filter box shows the query already
+        false
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.filter_timeout.is_none() && self.props.chat_list_query != props.chat_list_query {
+            self.query = props.chat_list_query.to_string();
+        }
         self.props.neq_assign(props)
     }
 
@@ -57,6 +108,7 @@ impl Component for Chatlist {
         let unpin_cb = self.props.unpin_chat_callback.clone();
         let archive_cb = self.props.archive_chat_callback.clone();
         let unarchive_cb = self.props.unarchive_chat_callback.clone();
+        let mute_cb = self.props.mute_chat_callback.clone();
 
         let selected_chat_id = self.props.selected_chat_id.clone();
 
@@ -96,6 +148,24 @@ impl Component for Chatlist {
                     actions.insert("Archive".to_string(), archive_callback);
                 }
 
+                let durations: &[_] = if chat.is_muted {
+                    &[("Unmute", MuteDuration::NotMuted)]
+                } else {
+                    &[
+                        ("Mute for 1 hour", MuteDuration::OneHour),
+                        ("Mute for 8 hours", MuteDuration::EightHours),
+                        ("Mute for 1 week", MuteDuration::OneWeek),
+                        ("Mute forever", MuteDuration::Forever),
+                    ]
+                };
+                for (name, duration) in durations.iter().copied() {
+                    let mute_cb = mute_cb.clone();
+                    let mute_callback: Callback<()> =
+                        (move |_| mute_cb.emit((account.clone(), chat_id, duration))).into();
+
+                    actions.insert(name.to_string(), mute_callback);
+                }
+
                 html! {
                     <ContextMenu actions=actions>
                       <Chat
@@ -122,15 +192,40 @@ impl Component for Chatlist {
 
         let create_chat_cb = self.props.create_chat_callback.clone();
         let onclick: Callback<_> = (move |_| create_chat_cb.emit(())).into();
+        let search_cb = self.props.search_callback.clone();
+        let onsearch: Callback<_> = (move |_| search_cb.emit(())).into();
 
-        html! {
-            <div class="chats">
+        let header = if self.props.chat_list_flags.archived_only {
+            let cb = self.props.show_archived_callback.clone();
+            let onback: Callback<_> = (move |_| cb.emit(false)).into();
+
+            html! {
+                <div class="account-header">
+                    <div class="archived-back" title="Back to chats" onclick=onback>
+                        <div class="icon arrow-back"></div>
+                    </div>
+                    <div class="account-info">{"Archived chats"}</div>
+                </div>
+            }
+        } else {
+            html! {
                 <div class="account-header">
                     <div class="account-info">
                         {name}
                         {email}
                     </div>
+                    <div class="account-search" title="Search messages" onclick=onsearch>
+                        <div class="icon search"></div>
+                    </div>
                 </div>
+            }
+        };
+
+        html! {
+            <div class="chats">
+                { header }
+                { self.view_filter() }
+                { self.view_archived_link() }
                 <List<ChatState>
                     class="chat-list".to_string()
                     list=self.props.chats.clone()
@@ -147,6 +242,72 @@ impl Component for Chatlist {
     }
 }
 
+impl Chatlist {
+    fn view_archived_link(&self) -> Html {
+        let archived_len = *self.props.archived_len;
+        if self.props.chat_list_flags.archived_only || archived_len == 0 {
+            return html! {};
+        }
+
+        let cb = self.props.show_archived_callback.clone();
+        let onclick: Callback<_> = (move |_| cb.emit(true)).into();
+
+        html! {
+            <div class="archived-link" onclick=onclick>
+                <div class="icon archive small"></div>
+                { format!("Archived chats ({})", archived_len) }
+            </div>
+        }
+    }
+
+    fn view_filter(&self) -> Html {
+        let oninput = self.link.callback(|e: InputData| Msg::FilterInput(e.value));
+
+        let flags = *self.props.chat_list_flags;
+        let toggle = |name: &str, active: bool, flags: ChatListFlags| {
+            let cb = self.props.flags_callback.clone();
+            let onclick: Callback<_> = (move |_| cb.emit(flags)).into();
+            let class = if active {
+                "filter-toggle active"
+            } else {
+                "filter-toggle"
+            };
+
+            html! {
+                <div class=class onclick=onclick>{name}</div>
+            }
+        };
+        let unread_only = toggle(
+            "Unread",
+            flags.unread_only,
+            ChatListFlags {
+                unread_only: !flags.unread_only,
+                ..flags
+            },
+        );
+        let groups_only = toggle(
+            "Groups",
+            flags.groups_only,
+            ChatListFlags {
+                groups_only: !flags.groups_only,
+                ..flags
+            },
+        );
+
+        html! {
+            <div class="chat-list-filter">
+                <input
+                    type="text"
+                    placeholder="Filter chats"
+                    value=self.query.clone()
+                    oninput=oninput />
+                { unread_only }
+                { groups_only }
+            </div>
+        }
+    }
+}
+
 #[derive(Properties, PartialEq, Clone)]
 pub struct ChatProps {
     pub chat: ChatState,
@@ -183,9 +344,16 @@ impl Component for Chat {
         } else {
             html! {}
         };
+        let muted = if chat.is_muted {
+            html! {
+                <div class="icon notifications-off small" title="Muted"></div>
+            }
+        } else {
+            html! {}
+        };
         let image_style = format!("background-color: #{:06X}", chat.color);
         let image = if let Some(ref profile_image) = chat.profile_image {
-            let src = format!("asset://{}", profile_image.to_string_lossy());
+            let src = asset::url(profile_image);
 
             html! {
                 <img
@@ -213,7 +381,10 @@ impl Component for Chat {
             <div class=class_name onclick=onclick key=chat.id>
                 <div class="chat-icon">{image}</div>
                 <div class="chat-content">
-                  <div class="chat-header">{&chat.name}</div>
+                  <div class="chat-header">
+                    {&chat.name}
+                    { muted }
+                  </div>
                   <div class="chat-preview">{&chat.preview}</div>
                 </div>
                 <div class="chat-badge">
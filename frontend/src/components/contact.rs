@@ -0,0 +1,110 @@
+use chrono::prelude::*;
+use shared::ContactInfo;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+use crate::asset;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub contact: ContactInfo,
+    pub rename_callback: Callback<String>,
+    pub close_callback: Callback<()>,
+}
+
+pub struct ContactPanel {
+    props: Props,
+}
+
+impl Component for ContactPanel {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        ContactPanel { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        let contact = &self.props.contact;
+        let cb = self.props.close_callback.clone();
+        let close_cb: Callback<_> = (move |_| cb.emit(())).into();
+
+        let cb = self.props.rename_callback.clone();
+        let display_name = contact.display_name.clone();
+        let onrename: Callback<_> = (move |e: ChangeData| {
+            if let ChangeData::Value(name) = e {
+                if name.trim() != display_name {
+                    cb.emit(name);
+                }
+            }
+        })
+        .into();
+
+        let image = if let Some(ref profile_image) = contact.profile_image {
+            html! {
+                <img class="contact-image" src={asset::url(profile_image)} alt="contact avatar" />
+            }
+        } else {
+            let image_style = format!("background-color: #{:06X}", contact.color);
+            html! {
+                <div class="contact-image letter-icon" style={image_style}>
+                    {contact.display_name.chars().next().unwrap_or_default()}
+                </div>
+            }
+        };
+
+        let last_seen = match contact.last_seen {
+            Some(ref last_seen) => Local
+                .from_utc_datetime(&last_seen.naive_utc())
+                .format("%c")
+                .to_string(),
+            None => "Never".to_string(),
+        };
+        let status = if contact.status.is_empty() {
+            html! {}
+        } else {
+            html! {
+                <>
+                    <dt>{"Status"}</dt>
+                    <dd class="contact-status">{ &contact.status }</dd>
+                </>
+            }
+        };
+
+        html! {
+            <div class="message-info-panel contact-panel">
+                <div class="message-info-header">
+                    <div class="message-info-title">{"Contact"}</div>
+                    <div class="icon close" title="Close" onclick=close_cb></div>
+                </div>
+                <div class="contact-summary">
+                    { image }
+                    <input
+                        class="contact-name"
+                        type="text"
+                        title="Rename contact"
+                        value=contact.display_name.clone()
+                        onchange=onrename />
+                </div>
+                <dl>
+                    <dt>{"Address"}</dt>
+                    <dd>{ &contact.mail }</dd>
+                    { status }
+                    <dt>{"Last seen"}</dt>
+                    <dd>{ last_seen }</dd>
+                    <dt>{"Encryption"}</dt>
+                    <dd>{ if contact.is_verified { "Verified" } else { "Not verified" } }</dd>
+                    { if contact.is_blocked { html! { <dd class="contact-blocked">{"Blocked"}</dd> } } else { html! {} } }
+                </dl>
+            </div>
+        }
+    }
+}
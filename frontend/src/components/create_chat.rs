@@ -13,6 +13,8 @@ pub struct Props {
     pub contact_cb: Callback<()>,
     pub create_chat_cb: Callback<HashSet<u32>>,
     pub create_group_chat_cb: Callback<(HashSet<u32>, String)>,
+    /// Creates a contact from a name and an address and opens a chat with it.
+    pub create_contact_cb: Callback<(String, String)>,
     pub add_chat_close_cb: Callback<()>,
 }
 
@@ -22,14 +24,56 @@ pub struct CreateChat {
     selected: HashSet<u32>,
     query: String,
     group_name_input_ref: NodeRef,
+    contact_name_input_ref: NodeRef,
 }
 
 pub enum Msg {
     Toggle(u32),
     Send,
+    ChatWithAddress,
     OnInputQuery(String),
 }
 
+impl CreateChat {
+    /// Whether the query looks like an address that is not a contact yet.
+    fn is_new_address(&self) -> bool {
+        let query = self.query.trim();
+        if !query.contains('@') || query.contains(char::is_whitespace) {
+            return false;
+        }
+        match &*self.props.contacts {
+            Some(contacts) => !contacts
+                .iter()
+                .any(|contact| contact.mail.eq_ignore_ascii_case(query)),
+            None => true,
+        }
+    }
+
+    fn view_new_contact(&self) -> Html {
+        if !self.is_new_address() {
+            return html! {};
+        }
+
+        let onclick = self.link.callback(|_| Msg::ChatWithAddress);
+        html! {
+            <div class="wrapper open new-contact">
+                <div class="group-name">
+                    <label for="contact-name">{"Name (optional): "}</label>
+                    <input
+                        id="contact-name"
+                        ref=self.contact_name_input_ref.clone()
+                        size="1"
+                        alt="Contact name"/>
+                </div>
+                <div class="contact" onclick=onclick>
+                    <h2>{"Chat with address"}</h2>
+                    <p>{self.query.trim()}</p>
+                </div>
+            </div>
+        }
+    }
+}
+
 impl Component for CreateChat {
     type Message = Msg;
     type Properties = Props;
@@ -53,6 +97,7 @@ impl Component for CreateChat {
             selected: HashSet::new(),
             query: String::new(),
             group_name_input_ref: NodeRef::default(),
+            contact_name_input_ref: NodeRef::default(),
         }
     }
 
@@ -87,6 +132,19 @@ impl Component for CreateChat {
                 }
                 true
             }
+            Msg::ChatWithAddress => {
+                let name = self
+                    .contact_name_input_ref
+                    .cast::<HtmlInputElement>()
+                    .map(|input| input.value())
+                    .unwrap_or_default();
+                info!("creating new 1o1 chat with {}", self.query);
+                self.props
+                    .create_contact_cb
+                    .emit((name, self.query.trim().to_string()));
+                self.props.add_chat_close_cb.emit(());
+                true
+            }
             Msg::OnInputQuery(change) => {
                 self.query = change;
                 true
@@ -156,6 +214,8 @@ impl Component for CreateChat {
                     </div>
                 </div>
 
+                { self.view_new_contact() }
+
                 <div class="contact-list">
                     <div>
                         {contacts}
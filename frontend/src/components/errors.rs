@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use shared::{ErrorInfo, ErrorKind, SharedAccountState};
+use yew::{html, Callback, Component, ComponentLink, Html, Properties, ShouldRender};
+use yewtil::{ptr::Irc, NeqAssign};
+
+/// A request that was answered with `Response::Error`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestFailure {
+    pub request: &'static str,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub accounts: Irc<HashMap<u32, SharedAccountState>>,
+    pub errors: Irc<Vec<ErrorInfo>>,
+    pub failures: Irc<Vec<RequestFailure>>,
+    pub dismiss_error_callback: Callback<u32>,
+    pub retry_error_callback: Callback<ErrorInfo>,
+    pub dismiss_failure_callback: Callback<usize>,
+}
+
+pub struct Errors {
+    props: Props,
+}
+
+impl Component for Errors {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Errors { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        if self.props.errors.is_empty() && self.props.failures.is_empty() {
+            return html! {};
+        }
+
+        let errors = self
+            .props
+            .errors
+            .iter()
+            .map(|error| self.view_error(error))
+            .collect::<Html>();
+
+        let failures = self
+            .props
+            .failures
+            .iter()
+            .enumerate()
+            .map(|(i, failure)| {
+                let cb = self.props.dismiss_failure_callback.clone();
+                let onclick: Callback<_> = (move |_| cb.emit(i)).into();
+
+                html! {
+                    <div class="error-item">
+                        <div class="error-text">
+                            <div class="error-title">{format!("{} failed", failure.request)}</div>
+                            <div class="error-message">{&failure.message}</div>
+                        </div>
+                        <button class="error-dismiss icon close small" onclick=onclick></button>
+                    </div>
+                }
+            })
+            .collect::<Html>();
+
+        html! {
+            <div class="errors">
+                { errors }
+                { failures }
+            </div>
+        }
+    }
+}
+
+impl Errors {
+    fn view_error(&self, error: &ErrorInfo) -> Html {
+        let id = error.id;
+        let cb = self.props.dismiss_error_callback.clone();
+        let dismiss: Callback<_> = (move |_| cb.emit(id)).into();
+
+        let retry = if error.retryable {
+            let cb = self.props.retry_error_callback.clone();
+            let error = error.clone();
+            let onclick: Callback<_> = (move |_| cb.emit(error.clone())).into();
+            html! {
+                <button class="error-retry" onclick=onclick>{"Retry"}</button>
+            }
+        } else {
+            html! {}
+        };
+
+        let account = error
+            .account_addr
+            .clone()
+            .or_else(|| {
+                error
+                    .account
+                    .and_then(|id| self.props.accounts.get(&id))
+                    .map(|account| account.email.clone())
+            })
+            .unwrap_or_default();
+        let local = Local.from_utc_datetime(&error.timestamp.naive_utc());
+
+        html! {
+            <div class="error-item" key=id>
+                <div class="error-text">
+                    <div class="error-title">{title(error.kind)}</div>
+                    <div class="error-account">{account}</div>
+                    <div class="error-message">{&error.message}</div>
+                    <div class="error-footer">
+                        <div class="error-timestamp">{local.format("%R")}</div>
+                        { retry }
+                    </div>
+                </div>
+                <button class="error-dismiss icon close small" onclick=dismiss></button>
+            </div>
+        }
+    }
+}
+
+fn title(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Login => "Login failed",
+        ErrorKind::Import => "Import failed",
+        ErrorKind::InvalidAccount => "Unknown account",
+        ErrorKind::NoAccountSelected => "No account selected",
+        ErrorKind::NoChatSelected => "No chat selected",
+        ErrorKind::IncompatibleProtocol => "Incompatible backend",
+        ErrorKind::InvalidRequest
+        | ErrorKind::ParseError
+        | ErrorKind::UnknownMethod
+        | ErrorKind::InvalidParams
+        | ErrorKind::Other => "Error",
+    }
+}
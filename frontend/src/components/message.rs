@@ -1,39 +1,88 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use chrono::prelude::*;
 
 use shared::*;
-use yew::{html, virtual_dom::VList, Component, ComponentLink, Html, Properties, ShouldRender};
+use yew::services::{interval::IntervalTask, IntervalService};
+use yew::{
+    html, virtual_dom::VList, Callback, Component, ComponentLink, Html, Properties, ShouldRender,
+};
 use yewtil::NeqAssign;
 
+use super::context_menu::ContextMenu;
+use crate::asset;
+
 #[derive(Properties, Clone, PartialEq)]
 pub struct Props {
     pub message: ChatMessage,
+    pub reply_callback: Callback<InnerChatMessage>,
+    pub delete_callback: Callback<u32>,
+    pub forward_callback: Callback<u32>,
+    pub info_callback: Callback<u32>,
+    /// Shows the contact with the given id.
+    pub contact_callback: Callback<u32>,
 }
 
 pub struct Message {
+    link: ComponentLink<Self>,
     message: ChatMessage,
+    reply_callback: Callback<InnerChatMessage>,
+    delete_callback: Callback<u32>,
+    forward_callback: Callback<u32>,
+    info_callback: Callback<u32>,
+    contact_callback: Callback<u32>,
+    /// Updates the remaining lifetime of disappearing messages.
+    countdown: Option<IntervalTask>,
+}
+
+pub enum Msg {
+    Tick,
 }
 
 impl Component for Message {
-    type Message = ();
+    type Message = Msg;
     type Properties = Props;
 
-    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
-        Message {
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        let mut message = Message {
+            link,
+            countdown: None,
             message: props.message,
-        }
+            reply_callback: props.reply_callback,
+            delete_callback: props.delete_callback,
+            forward_callback: props.forward_callback,
+            info_callback: props.info_callback,
+            contact_callback: props.contact_callback,
+        };
+        message.update_countdown();
+        message
     }
 
-    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
-        true
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Tick => true,
+        }
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.message.neq_assign(props.message)
+        // callbacks are recreated on every render of the list
+        self.reply_callback = props.reply_callback;
+        self.delete_callback = props.delete_callback;
+        self.forward_callback = props.forward_callback;
+        self.info_callback = props.info_callback;
+        self.contact_callback = props.contact_callback;
+        let changed = self.message.neq_assign(props.message);
+        if changed {
+            self.update_countdown();
+        }
+        changed
     }
 
     fn view(&self) -> Html {
         match &self.message {
             ChatMessage::Message(InnerChatMessage {
+                from_id,
                 from_color,
                 from_first_name,
                 from_profile_image,
@@ -47,6 +96,8 @@ impl Component for Message {
                 is_first,
                 state,
                 quote,
+                ephemeral_timer,
+                ephemeral_expires,
                 ..
             }) => {
                 let image = if *is_first {
@@ -54,7 +105,7 @@ impl Component for Message {
                         html! {
                             <img
                              class="image-icon"
-                             src={format!("asset://{}", profile_image.to_string_lossy())}
+                             src={asset::url(profile_image)}
                              alt="chat avatar"                             />
                         }
                     } else {
@@ -76,7 +127,7 @@ impl Component for Message {
                             html! {
                                 <div class="message-image">
                                   <img
-                                    src={format!("asset://{}", file.display())}
+                                    src={asset::url(file)}
                                     alt="image"
                                     height={(file_height).min(400).to_string()}
                                     width="auto" />
@@ -107,6 +158,24 @@ impl Component for Message {
                 let timestamp = html! {
                     <div class="message-timestamp">{local.format("%R")}</div>
                 };
+                let cb = self.contact_callback.clone();
+                let from_id = *from_id;
+                let show_contact: Callback<_> = (move |_| cb.emit(from_id)).into();
+
+                let ephemeral = if *ephemeral_timer > 0 {
+                    let remaining = match ephemeral_expires {
+                        Some(expires) => (*expires - Utc::now()).num_seconds().max(0),
+                        None => i64::from(*ephemeral_timer),
+                    };
+                    html! {
+                        <div class="message-ephemeral" title="Disappearing message">
+                            <div class="icon timer small"></div>
+                            { format_remaining(remaining) }
+                        </div>
+                    }
+                } else {
+                    html! {}
+                };
                 let status = {
                     let icon = match state.as_ref() {
                         "Delivered" | "Noticed" | "Seen" => "checkmark",
@@ -127,11 +196,14 @@ impl Component for Message {
                 } else if *is_first {
                     html! {
                         <div class="message-text">
-                            <div class="message-icon">{image}</div>
+                            <div class="message-icon" title="Show contact" onclick=show_contact>
+                                {image}
+                            </div>
                             <div class="message-body">
                                 <div class="message-header">
                                     <div class="message-sender">{&from_first_name}</div>
                                     { timestamp }
+                                    { ephemeral }
                                     { status }
                                 </div>
                                 { file }
@@ -146,6 +218,7 @@ impl Component for Message {
                             <div class="message-prefix">
                                 { status }
                                 { timestamp }
+                                { ephemeral }
                             </div>
                             <div class="message-body">
                                 { file }
@@ -157,9 +230,12 @@ impl Component for Message {
                 };
 
                 html! {
-                    <div class="message" key=*id>
-                    { content }
-                    </div>
+                    <ContextMenu actions=self.context_actions()>
+                      <div class="message" key=*id>
+                      { self.view_menu() }
+                      { content }
+                      </div>
+                    </ContextMenu>
                 }
             }
             ChatMessage::DayMarker(time) => {
@@ -174,6 +250,81 @@ impl Component for Message {
     }
 }
 
+impl Message {
+    fn update_countdown(&mut self) {
+        let expires = match &self.message {
+            ChatMessage::Message(message) => message.ephemeral_expires.is_some(),
+            ChatMessage::DayMarker(_) => false,
+        };
+
+        if !expires {
+            self.countdown = None;
+        } else if self.countdown.is_none() {
+            let tick = self.link.callback(|_| Msg::Tick);
+            self.countdown = Some(IntervalService::spawn(Duration::from_secs(1), tick));
+        }
+    }
+
+    fn view_menu(&self) -> Html {
+        match &self.message {
+            ChatMessage::Message(message) if !message.is_info => {
+                let cb = self.reply_callback.clone();
+                let message = message.clone();
+                let reply: Callback<_> = (move |_| cb.emit(message.clone())).into();
+
+                html! {
+                    <div class="message-menu">
+                        <div class="icon arrow-undo small" title="Reply" onclick=reply></div>
+                    </div>
+                }
+            }
+            _ => html! {},
+        }
+    }
+
+    fn context_actions(&self) -> HashMap<String, Callback<()>> {
+        let mut actions = HashMap::new();
+        if let ChatMessage::Message(message) = &self.message {
+            if !message.is_info {
+                let cb = self.reply_callback.clone();
+                let message = message.clone();
+                let reply: Callback<()> = (move |_| cb.emit(message.clone())).into();
+
+                actions.insert("Reply".to_string(), reply);
+
+                let cb = self.forward_callback.clone();
+                let id = message.id;
+                let forward: Callback<()> = (move |_| cb.emit(id)).into();
+
+                actions.insert("Forward".to_string(), forward);
+            }
+
+            let cb = self.delete_callback.clone();
+            let id = message.id;
+            let delete: Callback<()> = (move |_| cb.emit(id)).into();
+
+            actions.insert("Delete".to_string(), delete);
+
+            let cb = self.info_callback.clone();
+            let info: Callback<()> = (move |_| cb.emit(id)).into();
+
+            actions.insert("Info".to_string(), info);
+        }
+
+        actions
+    }
+}
+
+/// Short remaining time, like `5m`.
+fn format_remaining(secs: i64) -> String {
+    match secs {
+        s if s >= 24 * 60 * 60 => format!("{}d", s / (24 * 60 * 60)),
+        s if s >= 60 * 60 => format!("{}h", s / (60 * 60)),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
 fn process_text(source: impl AsRef<str>) -> Html {
     let link_finder = linkify::LinkFinder::new();
     link_finder
@@ -0,0 +1,72 @@
+use chrono::prelude::*;
+use shared::MessageInfo;
+use yew::prelude::*;
+use yewtil::NeqAssign;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub info: MessageInfo,
+    pub close_callback: Callback<()>,
+}
+
+pub struct MessageInfoPanel {
+    props: Props,
+}
+
+impl Component for MessageInfoPanel {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        MessageInfoPanel { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        let info = &self.props.info;
+        let cb = self.props.close_callback.clone();
+        let close_cb: Callback<_> = (move |_| cb.emit(())).into();
+
+        let format_time = |time: &DateTime<Utc>| {
+            Local
+                .from_utc_datetime(&time.naive_utc())
+                .format("%c")
+                .to_string()
+        };
+        let received = match info.received {
+            Some(ref received) => html! {
+                <>
+                    <dt>{"Received"}</dt>
+                    <dd>{ format_time(received) }</dd>
+                </>
+            },
+            None => html! {},
+        };
+
+        html! {
+            <div class="message-info-panel">
+                <div class="message-info-header">
+                    <div class="message-info-title">{"Message info"}</div>
+                    <div class="icon close" title="Close" onclick=close_cb></div>
+                </div>
+                <dl>
+                    <dt>{"State"}</dt>
+                    <dd>{ &info.state }</dd>
+                    <dt>{"Sent"}</dt>
+                    <dd>{ format_time(&info.sent) }</dd>
+                    { received }
+                    <dt>{"Encryption"}</dt>
+                    <dd>{ if info.is_encrypted { "End-to-end encrypted" } else { "Not encrypted" } }</dd>
+                </dl>
+                <pre class="message-info-text">{ &info.text }</pre>
+            </div>
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use log::*;
 use yew::{html, Callback, Component, ComponentLink, Html, NodeRef, Properties, ShouldRender};
-use yewtil::NeqAssign;
+use yewtil::{ptr::Irc, NeqAssign};
 
 #[derive(Properties, Clone, PartialEq)]
 pub struct Props {
     pub send_callback: Callback<String>,
+    /// The text to show, e.g. the draft of the chat.
+    pub text: Irc<String>,
+    pub input_callback: Callback<String>,
 }
 
 pub struct MessageInput {
@@ -18,6 +21,16 @@ impl MessageInput {
     fn input(&self) -> Option<web_sys::HtmlInputElement> {
         self.input_ref.cast::<web_sys::HtmlInputElement>()
     }
+
+    /// Shows the text of the props, unless it is what the user typed anyway.
+    fn sync_text(&mut self) {
+        if let Some(ref input) = self.input() {
+            if input.value() != *self.props.text {
+                input.set_value(&self.props.text);
+                self.has_text = !self.props.text.is_empty();
+            }
+        }
+    }
 }
 
 pub enum Msg {
@@ -32,10 +45,10 @@ impl Component for MessageInput {
 
     fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
         MessageInput {
+            has_text: !props.text.is_empty(),
             props,
             link,
             input_ref: NodeRef::default(),
-            has_text: false,
         }
     }
 
@@ -49,6 +62,7 @@ impl Component for MessageInput {
                     }
                     input.set_value("");
                     self.has_text = false;
+                    self.props.input_callback.emit(String::new());
                 }
                 true
             }
@@ -63,7 +77,9 @@ impl Component for MessageInput {
             }
             Msg::OnInput(_) => {
                 if let Some(ref input) = self.input() {
-                    self.has_text = !input.value().is_empty();
+                    let text = input.value();
+                    self.has_text = !text.is_empty();
+                    self.props.input_callback.emit(text);
                 }
                 true
             }
@@ -71,7 +87,17 @@ impl Component for MessageInput {
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
-        self.props.neq_assign(props)
+        let changed = self.props.neq_assign(props);
+        if changed {
+            self.sync_text();
+        }
+        changed
+    }
+
+    fn rendered(&mut self, first_render: bool) {
+        if first_render {
+            self.sync_text();
+        }
     }
 
     fn view(&self) -> Html {
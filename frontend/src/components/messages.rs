@@ -15,6 +15,11 @@ pub struct Props {
     pub messages_len: Irc<usize>,
     pub selected_chat_id: Irc<Option<u32>>,
     pub fetch_callback: Callback<(usize, usize)>,
+    pub reply_callback: Callback<InnerChatMessage>,
+    pub delete_callback: Callback<u32>,
+    pub forward_callback: Callback<u32>,
+    pub info_callback: Callback<u32>,
+    pub contact_callback: Callback<u32>,
 }
 
 pub struct Messages {
@@ -39,9 +44,22 @@ impl Component for Messages {
     }
 
     fn view(&self) -> Html {
+        let reply_callback = self.props.reply_callback.clone();
+        let delete_callback = self.props.delete_callback.clone();
+        let forward_callback = self.props.forward_callback.clone();
+        let info_callback = self.props.info_callback.clone();
+        let contact_callback = self.props.contact_callback.clone();
         let render_element: Rc<dyn Fn(ChatMessage) -> Html> =
             Rc::new(move |msg: ChatMessage| -> Html {
-                html! { <Message message=msg /> }
+                html! {
+                    <Message
+                        message=msg
+                        reply_callback=reply_callback.clone()
+                        delete_callback=delete_callback.clone()
+                        forward_callback=forward_callback.clone()
+                        info_callback=info_callback.clone()
+                        contact_callback=contact_callback.clone() />
+                }
             });
         info!("messages {:?}", self.props.messages.len());
         if self.props.messages.len() > 0 {
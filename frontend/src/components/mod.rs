@@ -1,5 +1,6 @@
 pub mod chatlist;
 pub mod message;
+pub mod message_info;
 pub mod message_input;
 pub mod messages;
 pub mod sidebar;
@@ -8,6 +9,12 @@ pub mod list;
 pub mod modal;
 
 pub mod chat;
+pub mod chat_info;
+pub mod chat_picker;
+pub mod contact;
 pub mod context_menu;
 pub mod create_chat;
+pub mod errors;
+pub mod search;
+pub mod settings;
 pub mod windowmanager;
@@ -0,0 +1,152 @@
+use chrono::prelude::*;
+use shared::{ChatState, SearchResult};
+use yew::prelude::*;
+use yewtil::{ptr::Irc, NeqAssign};
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub results: Irc<Option<(String, Vec<SearchResult>)>>,
+    pub selected_chat: Irc<Option<ChatState>>,
+    /// Searches for the query, in the given chat or in all chats.
+    pub search_callback: Callback<(String, Option<u32>)>,
+    pub select_callback: Callback<u32>,
+    pub close_callback: Callback<()>,
+}
+
+pub struct Search {
+    link: ComponentLink<Self>,
+    props: Props,
+    query: String,
+    in_chat: bool,
+}
+
+pub enum Msg {
+    Query(String),
+    ToggleInChat,
+}
+
+impl Search {
+    fn search(&self) {
+        if self.query.trim().is_empty() {
+            return;
+        }
+
+        let chat_id = if self.in_chat {
+            self.props
+                .selected_chat
+                .as_ref()
+                .as_ref()
+                .map(|chat| chat.id)
+        } else {
+            None
+        };
+        self.props
+            .search_callback
+            .emit((self.query.trim().to_string(), chat_id));
+    }
+
+    fn view_result(&self, result: &SearchResult) -> Html {
+        let cb = self.props.select_callback.clone();
+        let chat_id = result.chat_id;
+        let onclick: Callback<_> = (move |_| cb.emit(chat_id)).into();
+
+        let local = Local.from_utc_datetime(&result.message.timestamp.naive_utc());
+
+        html! {
+            <div key=result.message.id class="search-result" onclick=onclick>
+                <div class="search-result-header">
+                    <div class="search-result-chat">{ &result.chat_name }</div>
+                    <div class="search-result-timestamp">{ local.format("%x %R") }</div>
+                </div>
+                <div class="search-result-text">
+                    <span class="search-result-sender">{ &result.message.from_first_name }{": "}</span>
+                    { &result.snippet }
+                </div>
+            </div>
+        }
+    }
+}
+
+impl Component for Search {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Search {
+            link,
+            props,
+            query: String::new(),
+            in_chat: false,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        match msg {
+            Msg::Query(query) => {
+                self.query = query;
+                self.search();
+                false
+            }
+            Msg::ToggleInChat => {
+                self.in_chat = !self.in_chat;
+                self.search();
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        let cb = self.props.close_callback.clone();
+        let close_cb: Callback<_> = (move |_| cb.emit(())).into();
+        let onchange = self.link.callback(|e: ChangeData| match e {
+            ChangeData::Value(query) => Msg::Query(query),
+            _ => Msg::Query(String::new()),
+        });
+
+        let in_chat = match &*self.props.selected_chat {
+            Some(chat) => {
+                let ontoggle = self.link.callback(|_| Msg::ToggleInChat);
+                html! {
+                    <label class="search-in-chat">
+                        <input type="checkbox" checked=self.in_chat onclick=ontoggle />
+                        { format!("Only in {}", chat.name) }
+                    </label>
+                }
+            }
+            None => html! {},
+        };
+
+        let results = match &*self.props.results {
+            Some((query, results)) if results.is_empty() => html! {
+                <p class="text-center">{ format!("No messages found for \"{}\"", query) }</p>
+            },
+            Some((_, results)) => html! {
+                { for results.iter().map(|result| self.view_result(result)) }
+            },
+            None => html! {},
+        };
+
+        html! {
+            <div class="create-chat search-panel">
+                <div class="search">
+                    <button id="close" onclick=close_cb> <div class="icon arrow-back" /> </button>
+                    <input
+                        size="1"
+                        onchange=onchange
+                        id="search-bar"
+                        type="text"
+                        placeholder="Search messages" />
+                </div>
+                { in_chat }
+
+                <div class="contact-list">
+                    { results }
+                </div>
+            </div>
+        }
+    }
+}
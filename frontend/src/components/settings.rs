@@ -0,0 +1,97 @@
+use shared::ContactInfo;
+use yew::prelude::*;
+use yewtil::{ptr::Irc, NeqAssign};
+
+use crate::asset;
+
+/// Settings of the selected account.
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub blocked_contacts: Irc<Option<Vec<ContactInfo>>>,
+    pub unblock_callback: Callback<u32>,
+    pub close_callback: Callback<()>,
+}
+
+pub struct Settings {
+    props: Props,
+}
+
+impl Component for Settings {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _link: ComponentLink<Self>) -> Self {
+        Settings { props }
+    }
+
+    fn update(&mut self, _msg: Self::Message) -> ShouldRender {
+        true
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props.neq_assign(props)
+    }
+
+    fn view(&self) -> Html {
+        let cb = self.props.close_callback.clone();
+        let close_cb: Callback<_> = (move |_| cb.emit(())).into();
+
+        let blocked = match &*self.props.blocked_contacts {
+            Some(contacts) if contacts.is_empty() => html! {
+                <p class="text-center">{"No blocked contacts"}</p>
+            },
+            Some(contacts) => html! {
+                { for contacts.iter().map(|contact| self.view_blocked_contact(contact)) }
+            },
+            None => html! {
+                <p class="text-center">{"Loading blocked contacts"}</p>
+            },
+        };
+
+        html! {
+            <div class="create-chat settings-panel">
+                <div class="search">
+                    <button id="close" onclick=close_cb> <div class="icon arrow-back" /> </button>
+                    <div class="picker-title">{"Settings"}</div>
+                </div>
+
+                <div class="contact-list">
+                    <div class="settings-section">{"Blocked contacts"}</div>
+                    { blocked }
+                </div>
+            </div>
+        }
+    }
+}
+
+impl Settings {
+    fn view_blocked_contact(&self, contact: &ContactInfo) -> Html {
+        let cb = self.props.unblock_callback.clone();
+        let contact_id = contact.id;
+        let onunblock: Callback<_> = (move |_| cb.emit(contact_id)).into();
+
+        let image = if let Some(ref profile_image) = contact.profile_image {
+            html! {
+                <img class="image-icon" src={asset::url(profile_image)} alt="contact avatar" />
+            }
+        } else {
+            let image_style = format!("background-color: #{:06X}", contact.color);
+            html! {
+                <div class="letter-icon" style={image_style}>
+                    {contact.display_name.chars().next().unwrap_or_default()}
+                </div>
+            }
+        };
+
+        html! {
+            <div key=contact.id class="contact blocked-contact">
+                <div class="blocked-contact-icon">{ image }</div>
+                <div class="blocked-contact-body">
+                    <h2>{ &contact.display_name }</h2>
+                    <p>{ &contact.mail }</p>
+                </div>
+                <button onclick=onunblock>{"Unblock"}</button>
+            </div>
+        }
+    }
+}
@@ -4,12 +4,15 @@ use shared::SharedAccountState;
 use yew::{html, Callback, Component, ComponentLink, Html, Properties, ShouldRender};
 use yewtil::{ptr::Irc, NeqAssign};
 
+use crate::asset;
+
 #[derive(Properties, Clone, PartialEq)]
 pub struct Props {
     pub accounts: Irc<HashMap<u32, SharedAccountState>>,
     pub selected_account: Irc<Option<u32>>,
     pub create_account_callback: Callback<()>,
     pub select_account_callback: Callback<u32>,
+    pub settings_callback: Callback<()>,
 }
 
 pub struct Sidebar {
@@ -36,6 +39,8 @@ impl Component for Sidebar {
         let cb = self.props.create_account_callback.clone();
         let onclick: Callback<_> = (move |_| cb.emit(())).into();
         let selected_account = self.props.selected_account.unwrap_or_default();
+        let cb = self.props.settings_callback.clone();
+        let onsettings: Callback<_> = (move |_| cb.emit(())).into();
 
         html! {
             <div class="sidebar">
@@ -49,7 +54,7 @@ impl Component for Sidebar {
                             cls += " active";
                         }
                         let image = if let Some(ref profile_image) = acc.profile_image {
-                            let src = format!("asset://{}", profile_image.to_string_lossy());
+                            let src = asset::url(profile_image);
 
                             html! {
                                 <img
@@ -74,6 +79,9 @@ impl Component for Sidebar {
                     <a class="account add" onclick=onclick>
                         <div class="icon add medium"></div>
                     </a>
+                    <a class="account settings" title="Settings" onclick=onsettings>
+                        <div class="icon settings medium"></div>
+                    </a>
                 </div>
             </div>
         }
@@ -18,7 +18,13 @@ pub struct Props {
 #[derive(Debug, Clone, PartialEq)]
 pub enum LeftPanel {
     Chats,
+    /// The chat list, showing only the archived chats.
+    Archived,
     NewChat,
+    /// Picking the chat to forward messages to.
+    Forward,
+    Search,
+    Settings,
 }
 
 impl Default for LeftPanel {
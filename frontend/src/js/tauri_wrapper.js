@@ -1,4 +1,34 @@
 
 export async function invoke_backup_import(){
     return Number(await window.__TAURI__.invoke("load_backup"));
-}
\ No newline at end of file
+}
+// the path of the picked image, or `null`
+export async function invoke_pick_image(){
+    return await window.__TAURI__.invoke("pick_image");
+}
+export async function invoke_backend_secret(){
+    return await window.__TAURI__.invoke("backend_secret");
+}
+
+export async function invoke_backend_url(){
+    return await window.__TAURI__.invoke("backend_url");
+}
+
+export function is_tauri(){
+    return window.__TAURI__ !== undefined;
+}
+
+// when served by `dc40-server` the page is opened as `http://host:port/#token=...`
+export function location_token(){
+    return new URLSearchParams(window.location.hash.slice(1)).get("token") || "";
+}
+
+export async function fetch_backend_url(){
+    const res = await fetch("backend");
+    if (!res.ok) {
+        throw await res.text();
+    }
+    const { port } = await res.json();
+    const scheme = window.location.protocol === "https:" ? "wss" : "ws";
+    return `${scheme}://${window.location.hostname}:${port}/`;
+}
@@ -4,6 +4,7 @@
 extern crate validator_derive;
 
 mod app;
+mod asset;
 mod components;
 
 pub fn main() {
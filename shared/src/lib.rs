@@ -4,9 +4,41 @@ use std::path::PathBuf;
 use chrono::{DateTime, Utc};
 use num_derive::{FromPrimitive, ToPrimitive};
 use serde::{Deserialize, Serialize};
+use strum_macros::{EnumDiscriminants, IntoStaticStr};
+
+/// Version of the wire protocol, bump it whenever the encoding of [`Request`]
+/// or [`Response`] changes.
+pub const PROTOCOL_VERSION: u32 = 22;
+
+/// Optional protocol features, agreed on during the [`Request::Hello`] handshake.
+pub mod capability {
+    /// The backend answers successful requests with [`Response::Ack`](super::Response::Ack).
+    pub const ACK: &str = "ack";
+}
+
+/// All capabilities supported by this build.
+pub const CAPABILITIES: &[&str] = &[capability::ACK];
+
+/// The id of the own contact, e.g. in [`Response::ChatMembers`].
+pub const CONTACT_ID_SELF: u32 = 1;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
+    /// Answer to [`Request::Hello`], carrying the agreed capabilities.
+    ///
+    /// Must stay the first variant, so that it decodes the same across protocol versions.
+    Welcome {
+        protocol_version: u32,
+        capabilities: Vec<String>,
+        /// Identifies the run of the backend, event numbers start anew with every run.
+        /// See [`Request::Resume`].
+        run_id: u64,
+        /// Why the backend refuses the connection, it closes it right after.
+        ///
+        /// Fields are only ever appended here, clients of other versions skip the
+        /// ones they don't know and can still read the refusal.
+        refusal: Option<String>,
+    },
     RemoteUpdate {
         state: State,
     },
@@ -19,6 +51,8 @@ pub enum Response {
     ChatList {
         range: (usize, usize),
         len: usize,
+        /// How many chats are archived, listed with [`ChatListFlags::archived_only`].
+        archived_len: usize,
         chats: Vec<ChatState>,
     },
     Account {
@@ -26,21 +60,93 @@ pub enum Response {
         chat: Option<ChatState>,
         chat_id: Option<u32>,
     },
+    /// `seq` increases with every event, across all accounts. See [`Request::Resume`].
     Event {
+        seq: u64,
         account: u32,
         event: Event,
     },
     Contacts(Vec<ContactInfo>),
+    /// The request with the given id was processed successfully.
+    Ack {
+        request_id: u32,
+    },
+    /// Processing a request failed. `request_id` is `None` if the request
+    /// could not be decoded.
+    Error {
+        request_id: Option<u32>,
+        kind: ErrorKind,
+        message: String,
+    },
+    /// Events were dropped because the client could not keep up, it should reload
+    /// what it shows. Followed by a [`Response::RemoteUpdate`].
+    Resync,
+    /// Answer to [`Request::SearchMessages`], newest messages first.
+    SearchResults {
+        query: String,
+        results: Vec<SearchResult>,
+    },
+    /// Answer to [`Request::GetMessageInfo`].
+    MessageInfo(MessageInfo),
+    /// Answer to [`Request::GetContactDetail`] and [`Request::RenameContact`].
+    ContactDetail(ContactInfo),
+    /// Answer to [`Request::GetChatMembers`], including the own contact.
+    ChatMembers {
+        chat_id: u32,
+        members: Vec<ContactInfo>,
+    },
+    /// Answer to [`Request::GetBlockedContacts`].
+    BlockedContacts {
+        account: u32,
+        contacts: Vec<ContactInfo>,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ErrorKind {
+    InvalidRequest,
+    InvalidAccount,
+    NoAccountSelected,
+    NoChatSelected,
+    IncompatibleProtocol,
+    Login,
+    Import,
+    Other,
+    /// JSON-RPC messages that are not JSON.
+    ParseError,
+    UnknownMethod,
+    InvalidParams,
+}
+
+/// An error that happened in the backend, outside of a specific request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ErrorInfo {
+    pub id: u32,
+    pub kind: ErrorKind,
+    pub account: Option<u32>,
+    /// The address of the account, which is removed again if it could not be set up.
+    pub account_addr: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    /// Whether it makes sense for the user to try again.
+    pub retryable: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ContactInfo {
     pub id: u32,
     pub display_name: String,
     pub mail: String,
+    pub profile_image: Option<PathBuf>,
+    pub color: u32,
+    /// The signature of the last message received from the contact.
+    pub status: String,
+    pub last_seen: Option<DateTime<Utc>>,
+    pub is_verified: bool,
+    pub is_blocked: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Event {
     Configure(Progress),
     Imex(Progress),
@@ -48,22 +154,27 @@ pub enum Event {
     MessagesChanged {
         chat_id: u32,
     },
+    /// A new message to notify about, not sent for muted chats.
     MessageIncoming {
         chat_id: u32,
         title: String,
         body: String,
     },
     Log(Log),
+    /// A contact, or all of them if `contact_id` is `None`, changed.
+    ContactsChanged {
+        contact_id: Option<u32>,
+    },
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Progress {
     Success,
     Error,
     Step(usize),
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Log {
     Info(String),
     Warning(String),
@@ -78,7 +189,7 @@ pub struct State {
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct SharedState {
     pub accounts: HashMap<u32, SharedAccountState>,
-    pub errors: Vec<String>,
+    pub errors: Vec<ErrorInfo>,
     pub selected_account: Option<u32>,
     pub selected_chat_id: Option<u32>,
     pub selected_chat: Option<ChatState>,
@@ -96,7 +207,7 @@ pub enum ChatMessage {
     DayMarker(DateTime<Utc>),
 }
 
-#[derive(Debug, Serialize, Clone, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
 pub struct InnerChatMessage {
     pub id: u32,
     pub from_id: u32,
@@ -113,6 +224,10 @@ pub struct InnerChatMessage {
     pub file_height: i32,
     pub file_width: i32,
     pub is_first: bool,
+    /// The disappearing timer of the message in seconds, `0` if it stays.
+    pub ephemeral_timer: u32,
+    /// When the message disappears, once its timer started.
+    pub ephemeral_expires: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Clone, Deserialize, PartialEq, Eq)]
@@ -130,11 +245,68 @@ pub struct ChatState {
     pub is_contact_request: bool,
     pub is_self_talk: bool,
     pub is_device_talk: bool,
+    /// The other member of a 1:1 chat.
+    pub contact_id: Option<u32>,
     pub chat_type: String,
     pub color: u32,
     pub member_count: usize,
     pub is_pinned: bool,
     pub is_archived: bool,
+    pub is_muted: bool,
+    /// After how many seconds new messages disappear, `0` if they stay.
+    pub ephemeral_timer: u32,
+    pub draft: Option<Draft>,
+}
+
+/// How long [`Request::MuteChat`] silences a chat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MuteDuration {
+    NotMuted,
+    OneHour,
+    EightHours,
+    OneWeek,
+    Forever,
+}
+
+/// Delivery details of a message.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MessageInfo {
+    pub msg_id: u32,
+    pub state: String,
+    pub sent: DateTime<Utc>,
+    pub received: Option<DateTime<Utc>>,
+    pub is_encrypted: bool,
+    /// Everything the core knows about the message, as text. Includes the read receipts,
+    /// errors and the Message-ID.
+    pub text: String,
+}
+
+/// Filters of [`Request::LoadChatList`].
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ChatListFlags {
+    /// Only chats with fresh messages.
+    pub unread_only: bool,
+    pub groups_only: bool,
+    /// Only the archived chats, which are left out otherwise.
+    pub archived_only: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchResult {
+    pub chat_id: u32,
+    pub chat_name: String,
+    pub message: InnerChatMessage,
+    /// The part of the text around the match.
+    pub snippet: String,
+}
+
+/// The message a user started to write in a chat, but did not send yet.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Draft {
+    pub text: Option<String>,
+    pub quote: Option<Box<InnerChatMessage>>,
+    pub file: Option<Attachment>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
@@ -159,8 +331,24 @@ pub struct SharedAccountState {
     pub display_name: Option<String>,
 }
 
+/// A [`Request`] together with a client chosen id, which the backend echoes
+/// back in [`Response::Ack`] or [`Response::Error`].
 #[derive(Debug, Serialize, Deserialize)]
+pub struct RequestEnvelope {
+    pub request_id: u32,
+    pub request: Request,
+}
+
+#[derive(Debug, Serialize, Deserialize, IntoStaticStr, EnumDiscriminants)]
+#[strum_discriminants(name(RequestMethod), derive(Deserialize))]
 pub enum Request {
+    /// First request on every connection.
+    ///
+    /// Must stay the first variant, so that it decodes the same across protocol versions.
+    Hello {
+        protocol_version: u32,
+        capabilities: Vec<String>,
+    },
     Login {
         email: String,
         password: String,
@@ -169,9 +357,13 @@ pub enum Request {
         account: u32,
         chat_id: u32,
     },
+    /// Loads a part of the chat list, only listing the chats matching `query` and `flags`.
     LoadChatList {
         start_index: usize,
         stop_index: usize,
+        query: Option<String>,
+        #[serde(default)]
+        flags: ChatListFlags,
     },
     LoadMessageList {
         start_index: usize,
@@ -219,7 +411,126 @@ pub enum Request {
     },
     GetContacts,
     CreateChat(HashSet<u32>),
+    /// Creates and selects the group, failing with the members that could not be added.
     CreateGroupChat(HashSet<u32>, String),
+    DismissError {
+        id: u32,
+    },
+    /// Sent after reconnecting, replays the events after `last_seq` of the run
+    /// `run_id`. If they are not kept anymore or the backend was restarted since,
+    /// it answers with [`Response::Resync`] instead.
+    Resume {
+        run_id: u64,
+        last_seq: u64,
+    },
+    /// Sends a message quoting `quote_msg_id` to the selected chat, with a text, a file
+    /// or both.
+    SendReply {
+        quote_msg_id: u32,
+        text: Option<String>,
+        file: Option<Attachment>,
+    },
+    /// Deletes the messages locally and from the server, the message list is refreshed
+    /// through [`Event::MessagesChanged`].
+    DeleteMessages {
+        account: u32,
+        msg_ids: Vec<u32>,
+    },
+    /// Forwards the messages, including their attachments, to another chat.
+    ForwardMessages {
+        account: u32,
+        msg_ids: Vec<u32>,
+        target_chat_id: u32,
+    },
+    /// Stores the draft of a chat, removing it if there is neither a text nor a file.
+    SetDraft {
+        account: u32,
+        chat_id: u32,
+        text: Option<String>,
+        quote_msg_id: Option<u32>,
+        file: Option<Attachment>,
+    },
+    /// Searches the messages of a chat, or of all chats if `chat_id` is `None`.
+    /// Only the newest matches are returned.
+    SearchMessages {
+        account: u32,
+        chat_id: Option<u32>,
+        query: String,
+    },
+    GetMessageInfo {
+        account: u32,
+        msg_id: u32,
+    },
+    /// Muted chats don't cause [`Event::MessageIncoming`] notifications.
+    MuteChat {
+        account: u32,
+        chat_id: u32,
+        duration: MuteDuration,
+    },
+    /// Lets new messages of the chat disappear after `timer` seconds, `0` disables it.
+    SetEphemeralTimer {
+        account: u32,
+        chat_id: u32,
+        timer: u32,
+    },
+    /// Creates a contact for the address and opens a 1:1 chat with it.
+    CreateContact {
+        name: String,
+        email: String,
+    },
+    GetContactDetail {
+        account: u32,
+        contact_id: u32,
+    },
+    /// Sets the name the contact is shown with, an empty name resets it.
+    RenameContact {
+        account: u32,
+        contact_id: u32,
+        name: String,
+    },
+    AddChatMember {
+        account: u32,
+        chat_id: u32,
+        contact_id: u32,
+    },
+    RemoveChatMember {
+        account: u32,
+        chat_id: u32,
+        contact_id: u32,
+    },
+    RenameChat {
+        account: u32,
+        chat_id: u32,
+        name: String,
+    },
+    /// Sets the group image to the file at `image`, `None` removes it.
+    SetChatProfileImage {
+        account: u32,
+        chat_id: u32,
+        image: Option<String>,
+    },
+    LeaveChat {
+        account: u32,
+        chat_id: u32,
+    },
+    GetChatMembers {
+        account: u32,
+        chat_id: u32,
+    },
+    GetBlockedContacts {
+        account: u32,
+    },
+    UnblockContact {
+        account: u32,
+        contact_id: u32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Attachment {
+    pub typ: Viewtype,
+    pub path: String,
+    pub mime: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromPrimitive, ToPrimitive)]
@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, SystemTime};
 
 use anyhow::{anyhow, bail, ensure, Result};
 use async_std::path::{Path, PathBuf};
@@ -6,11 +7,13 @@ use async_std::prelude::*;
 use async_std::sync::{Arc, RwLock};
 use chrono::prelude::*;
 use deltachat::chat::ChatVisibility;
+use deltachat::constants::{Chattype, DC_CONTACT_ID_SELF, DC_GCL_ARCHIVED_ONLY};
 use deltachat::{
     chat::{self, Chat, ChatId},
-    chatlist::Chatlist,
-    contact::Contact,
+    chatlist::{self, Chatlist},
+    contact::{Contact, Origin, VerifiedStatus},
     context::Context,
+    ephemeral,
     message::{self, MsgId},
     EventType,
 };
@@ -18,7 +21,13 @@ use lazy_static::lazy_static;
 use log::*;
 use num_traits::{FromPrimitive, ToPrimitive};
 use serde::Serialize;
-use shared::{ChatItem, ChatMessage, ChatState, InnerChatMessage, Login, Viewtype};
+use shared::{
+    Attachment, ChatItem, ChatListFlags, ChatMessage, ChatState, ContactInfo, Draft,
+    InnerChatMessage, Login, MessageInfo, MuteDuration, SearchResult, Viewtype,
+};
+
+/// How many messages a search returns at most.
+const MAX_SEARCH_RESULTS: usize = 100;
 
 lazy_static! {
     pub static ref HOME_DIR: PathBuf = dirs::home_dir()
@@ -37,8 +46,6 @@ sa::assert_impl_all!(Account: Send);
 #[derive(Debug)]
 pub struct AccountState {
     pub logged_in: Login,
-    pub selected_chat_id: Option<ChatId>,
-    pub selected_chat: Option<ChatState>,
 }
 
 impl Account {
@@ -46,8 +53,6 @@ impl Account {
         let account = Account {
             state: Arc::new(RwLock::new(AccountState {
                 logged_in: Login::default(),
-                selected_chat_id: None,
-                selected_chat: None,
             })),
         };
 
@@ -142,44 +147,76 @@ impl Account {
         context: &Context,
         start_index: usize,
         stop_index: usize,
-    ) -> Result<((usize, usize), usize, Vec<ChatState>)> {
+        query: Option<&str>,
+        flags: ChatListFlags,
+    ) -> Result<((usize, usize), usize, usize, Vec<ChatState>)> {
         ensure!(start_index <= stop_index, "invalid indicies");
 
-        let chatlist = Chatlist::try_load(&context, 0, None, None)
+        let listflags = if flags.archived_only {
+            DC_GCL_ARCHIVED_ONLY
+        } else {
+            0
+        };
+        let query = query.map(str::trim).filter(|query| !query.is_empty());
+        let archived_len = chatlist::dc_get_archived_cnt(&context)
+            .await
+            .map_err(|err| anyhow!("failed to count archived chats: {:?}", err))?;
+        let chatlist = Chatlist::try_load(&context, listflags, None, None)
             .await
             .map_err(|err| anyhow!("failed to load chats: {:?}", err))?;
+        let matches = match query {
+            Some(query) => Some(matching_chats(context, query).await?),
+            None => None,
+        };
+        let chat_ids = filter_chats(context, &chatlist, matches.as_ref(), flags).await?;
 
-        let total_len = chatlist.len();
+        let total_len = chat_ids.len();
         let len = stop_index.saturating_sub(start_index);
 
         let mut chats = Vec::with_capacity(len);
-        for i in start_index..=stop_index {
-            let chat_id = chatlist.get_chat_id(i);
+        for chat_id in chat_ids.into_iter().skip(start_index).take(len + 1) {
             let (_, chat_state) = load_chat_state(context.clone(), chat_id, &chatlist).await?;
             if let Some(s) = chat_state {
                 chats.push(s);
             }
         }
 
-        Ok(((start_index, stop_index), total_len, chats))
+        Ok(((start_index, stop_index), total_len, archived_len, chats))
     }
 
-    pub async fn select_chat(&self, context: &Context, chat_id: ChatId) -> Result<()> {
-        info!("selecting chat {:?}", chat_id);
-        let mut ls = self.state.write().await;
-        ls.selected_chat_id = Some(chat_id);
-        let chatlist = Chatlist::try_load(&context, 0, None, None)
+    pub async fn load_chat(&self, context: &Context, chat_id: ChatId) -> Result<Option<ChatState>> {
+        // archived chats are only part of the archived list
+        let chat = Chat::load_from_db(&context, chat_id)
+            .await
+            .map_err(|err| anyhow!("failed to load chat: {:?}", err))?;
+        let listflags = if chat.get_visibility() == ChatVisibility::Archived {
+            DC_GCL_ARCHIVED_ONLY
+        } else {
+            0
+        };
+        let chatlist = Chatlist::try_load(&context, listflags, None, None)
             .await
             .map_err(|err| anyhow!("failed to load chats: {:?}", err))?;
-        let (_, selected_chat) = load_chat_state(context.clone(), chat_id, &chatlist).await?;
-        ls.selected_chat = selected_chat;
+        let (_, chat) = load_chat_state(context.clone(), chat_id, &chatlist).await?;
+
+        Ok(chat)
+    }
+
+    /// Marks the chat as noticed and returns its state.
+    pub async fn select_chat(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+    ) -> Result<Option<ChatState>> {
+        info!("selecting chat {:?}", chat_id);
+        let selected_chat = self.load_chat(context, chat_id).await?;
 
         // mark as noticed
         chat::marknoticed_chat(context, chat_id)
             .await
             .map_err(|err| anyhow!("failed to mark noticed: {:?}", err))?;
 
-        Ok(())
+        Ok(selected_chat)
     }
     pub async fn pin_chat(&self, context: &Context, chat_id: ChatId) -> Result<()> {
         info!("pinning chat: {:?}", chat_id);
@@ -221,45 +258,148 @@ impl Account {
         Ok(())
     }
 
+    pub async fn mute_chat(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        duration: MuteDuration,
+    ) -> Result<()> {
+        info!("muting chat {:?}: {:?}", chat_id, duration);
+
+        let hours = |hours: u64| {
+            chat::MuteDuration::Until(SystemTime::now() + Duration::from_secs(hours * 60 * 60))
+        };
+        let duration = match duration {
+            MuteDuration::NotMuted => chat::MuteDuration::NotMuted,
+            MuteDuration::OneHour => hours(1),
+            MuteDuration::EightHours => hours(8),
+            MuteDuration::OneWeek => hours(7 * 24),
+            MuteDuration::Forever => chat::MuteDuration::Forever,
+        };
+
+        chat::set_muted(&context, chat_id, duration)
+            .await
+            .map_err(|err| anyhow!("failed to mute chat: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn add_chat_member(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        contact_id: u32,
+    ) -> Result<()> {
+        info!("adding contact {} to chat {:?}", contact_id, chat_id);
+        ensure!(
+            chat::add_contact_to_chat(&context, chat_id, contact_id).await,
+            "failed to add contact {} to chat {}",
+            contact_id,
+            chat_id
+        );
+
+        Ok(())
+    }
+
+    pub async fn remove_chat_member(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        contact_id: u32,
+    ) -> Result<()> {
+        info!("removing contact {} from chat {:?}", contact_id, chat_id);
+        chat::remove_contact_from_chat(&context, chat_id, contact_id)
+            .await
+            .map_err(|err| anyhow!("failed to remove contact from chat: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn rename_chat(&self, context: &Context, chat_id: ChatId, name: &str) -> Result<()> {
+        info!("renaming chat {:?} to {:?}", chat_id, name);
+        chat::set_chat_name(&context, chat_id, name)
+            .await
+            .map_err(|err| anyhow!("failed to rename chat: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn set_chat_profile_image(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        image: Option<&str>,
+    ) -> Result<()> {
+        info!("setting image of chat {:?} to {:?}", chat_id, image);
+        // an empty path removes the image
+        chat::set_chat_profile_image(&context, chat_id, image.unwrap_or_default())
+            .await
+            .map_err(|err| anyhow!("failed to set chat image: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn leave_chat(&self, context: &Context, chat_id: ChatId) -> Result<()> {
+        info!("leaving chat {:?}", chat_id);
+        chat::remove_contact_from_chat(&context, chat_id, DC_CONTACT_ID_SELF)
+            .await
+            .map_err(|err| anyhow!("failed to leave chat: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn set_ephemeral_timer(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        timer: u32,
+    ) -> Result<()> {
+        info!("setting ephemeral timer of {:?} to {}s", chat_id, timer);
+
+        chat_id
+            .set_ephemeral_timer(&context, ephemeral::Timer::from_u32(timer))
+            .await
+            .map_err(|err| anyhow!("failed to set ephemeral timer: {}", err))?;
+
+        Ok(())
+    }
+
     pub async fn load_message_list(
         &self,
         context: &Context,
+        chat_id: ChatId,
         range: Option<(usize, usize)>,
     ) -> Result<(u32, (usize, usize), Vec<ChatItem>, Vec<ChatMessage>)> {
-        let chat_id = self.state.read().await.selected_chat_id.clone();
-        if let Some(chat_id) = chat_id {
-            info!("loading {:?} msgs", chat_id);
-
-            let (chat_id, range, chat_items, chat_messages) =
-                refresh_message_list(context.clone(), chat_id, range).await?;
-
-            let msg_ids: Vec<_> = chat_messages
-                .iter()
-                .filter_map(|item| match item {
-                    ChatMessage::Message(inner) => Some(message::MsgId::new(inner.id)),
-                    ChatMessage::DayMarker(..) => None,
-                })
-                .collect();
-
-            {
-                let ctx = context.clone();
-                message::markseen_msgs(&ctx, msg_ids).await?;
-            }
-
-            Ok((chat_id, range, chat_items, chat_messages))
-        } else {
-            bail!("failed to load message list, no chat selected");
+        info!("loading {:?} msgs", chat_id);
+
+        let (chat_id, range, chat_items, chat_messages) =
+            refresh_message_list(context.clone(), chat_id, range).await?;
+
+        let msg_ids: Vec<_> = chat_messages
+            .iter()
+            .filter_map(|item| match item {
+                ChatMessage::Message(inner) => Some(message::MsgId::new(inner.id)),
+                ChatMessage::DayMarker(..) => None,
+            })
+            .collect();
+
+        {
+            let ctx = context.clone();
+            message::markseen_msgs(&ctx, msg_ids).await?;
         }
+
+        Ok((chat_id, range, chat_items, chat_messages))
     }
 
-    pub async fn send_text_message(&self, context: &Context, text: String) -> Result<()> {
-        if let Some(chat_id) = self.state.read().await.selected_chat_id {
-            chat::send_text_msg(&context, chat_id, text)
-                .await
-                .map_err(|err| anyhow!("failed to send message: {}", err))?;
-        } else {
-            bail!("no chat selected, can not send message");
-        }
+    pub async fn send_text_message(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        text: String,
+    ) -> Result<()> {
+        chat::send_text_msg(&context, chat_id, text)
+            .await
+            .map_err(|err| anyhow!("failed to send message: {}", err))?;
 
         Ok(())
     }
@@ -267,25 +407,165 @@ impl Account {
     pub async fn send_file_message(
         &self,
         context: &Context,
+        chat_id: ChatId,
         typ: Viewtype,
         path: String,
         text: Option<String>,
         mime: Option<String>,
     ) -> Result<()> {
-        if let Some(chat_id) = self.state.read().await.selected_chat_id {
-            let mut msg = message::Message::new(
-                deltachat::constants::Viewtype::from_i32(typ.to_i32().unwrap()).unwrap(),
-            );
-            msg.set_text(text);
-            msg.set_file(path, mime.as_deref());
-
-            chat::send_msg(&context, chat_id, &mut msg)
+        let mut msg = message::Message::new(
+            deltachat::constants::Viewtype::from_i32(typ.to_i32().unwrap()).unwrap(),
+        );
+        msg.set_text(text);
+        msg.set_file(path, mime.as_deref());
+
+        chat::send_msg(&context, chat_id, &mut msg)
+            .await
+            .map_err(|err| anyhow!("failed to send message: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn send_reply(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        quote_msg_id: MsgId,
+        text: Option<String>,
+        file: Option<Attachment>,
+    ) -> Result<()> {
+        ensure!(
+            text.is_some() || file.is_some(),
+            "a reply needs a text or a file"
+        );
+
+        let mut msg = build_message(context, text, Some(quote_msg_id), file).await?;
+        chat::send_msg(&context, chat_id, &mut msg)
+            .await
+            .map_err(|err| anyhow!("failed to send message: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn delete_messages(&self, context: &Context, msg_ids: Vec<MsgId>) -> Result<()> {
+        info!("deleting msgs: {:?}", msg_ids);
+
+        message::delete_msgs(&context, &msg_ids)
+            .await
+            .map_err(|err| anyhow!("failed to delete messages: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn forward_messages(
+        &self,
+        context: &Context,
+        msg_ids: Vec<MsgId>,
+        chat_id: ChatId,
+    ) -> Result<()> {
+        info!("forwarding msgs {:?} to {:?}", msg_ids, chat_id);
+
+        chat::forward_msgs(&context, &msg_ids, chat_id)
+            .await
+            .map_err(|err| anyhow!("failed to forward messages: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn get_message_info(&self, context: &Context, msg_id: MsgId) -> Result<MessageInfo> {
+        let msg = message::Message::load_from_db(&context, msg_id)
+            .await
+            .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+        let text = message::get_msg_info(&context, msg_id)
+            .await
+            .map_err(|err| anyhow!("failed to load msg info: {}: {}", msg_id, err))?;
+
+        let received = match msg.get_received_timestamp() {
+            0 => None,
+            ts => Some(get_timestamp(ts)),
+        };
+
+        Ok(MessageInfo {
+            msg_id: msg_id.to_u32(),
+            state: msg.get_state().to_string(),
+            sent: get_timestamp(msg.get_timestamp()),
+            received,
+            is_encrypted: msg.get_showpadlock(),
+            text,
+        })
+    }
+
+    pub async fn search_messages(
+        &self,
+        context: &Context,
+        chat_id: Option<ChatId>,
+        query: &str,
+    ) -> Result<Vec<SearchResult>> {
+        info!("searching {:?} for {:?}", chat_id, query);
+
+        let mut msg_ids = context
+            .search_msgs(chat_id, query)
+            .await
+            .map_err(|err| anyhow!("failed to search messages: {}", err))?;
+        // chat searches come oldest first, only the newest results are kept
+        if chat_id.is_some() {
+            msg_ids.reverse();
+        }
+        msg_ids.truncate(MAX_SEARCH_RESULTS);
+
+        let mut contacts = HashMap::new();
+        let mut chats = HashMap::new();
+        let mut results = Vec::with_capacity(msg_ids.len());
+        for msg_id in msg_ids {
+            let msg = message::Message::load_from_db(&context, msg_id)
                 .await
-                .map_err(|err| anyhow!("failed to send message: {}", err))?;
-        } else {
-            bail!("no chat selected, can not send message");
+                .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+
+            let chat_id = msg.get_chat_id();
+            let chat_name = match chats.get(&chat_id) {
+                Some(name) => name,
+                None => {
+                    let chat = Chat::load_from_db(&context, chat_id).await?;
+                    chats.insert(chat_id, chat.get_name().to_string());
+                    chats.get(&chat_id).unwrap()
+                }
+            }
+            .clone();
+
+            let snippet = snippet(msg.get_text().as_deref().unwrap_or_default(), query);
+            results.push(SearchResult {
+                chat_id: chat_id.to_u32(),
+                chat_name,
+                message: load_summary(&context, &mut contacts, msg).await?,
+                snippet,
+            });
         }
 
+        results.sort_by(|a, b| b.message.timestamp.cmp(&a.message.timestamp));
+
+        Ok(results)
+    }
+
+    /// Stores the draft of the chat, or removes it if there is neither a text nor a file.
+    pub async fn set_draft(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+        text: Option<String>,
+        quote_msg_id: Option<MsgId>,
+        file: Option<Attachment>,
+    ) -> Result<()> {
+        let mut msg = if text.is_some() || file.is_some() {
+            Some(build_message(context, text, quote_msg_id, file).await?)
+        } else {
+            None
+        };
+
+        chat_id
+            .set_draft(&context, msg.as_mut())
+            .await
+            .map_err(|err| anyhow!("failed to set draft: {}", err))?;
+
         Ok(())
     }
 
@@ -298,6 +578,77 @@ impl Account {
         chat_id.block(&context).await?;
         Ok(())
     }
+
+    pub async fn create_contact(&self, context: &Context, name: &str, email: &str) -> Result<u32> {
+        info!("creating contact {}", email);
+        Contact::create(context, name, email)
+            .await
+            .map_err(|err| anyhow!("failed to create contact: {}", err))
+    }
+
+    pub async fn get_contact_detail(
+        &self,
+        context: &Context,
+        contact_id: u32,
+    ) -> Result<ContactInfo> {
+        load_contact_info(context, contact_id).await
+    }
+
+    pub async fn get_chat_members(
+        &self,
+        context: &Context,
+        chat_id: ChatId,
+    ) -> Result<Vec<ContactInfo>> {
+        let contact_ids = chat::get_chat_contacts(&context, chat_id)
+            .await
+            .map_err(|err| anyhow!("failed to load chat members: {}", err))?;
+
+        let mut members = Vec::with_capacity(contact_ids.len());
+        for contact_id in contact_ids {
+            members.push(load_contact_info(context, contact_id).await?);
+        }
+
+        Ok(members)
+    }
+
+    pub async fn get_blocked_contacts(&self, context: &Context) -> Result<Vec<ContactInfo>> {
+        let contact_ids = Contact::get_all_blocked(&context)
+            .await
+            .map_err(|err| anyhow!("failed to load blocked contacts: {}", err))?;
+
+        let mut contacts = Vec::with_capacity(contact_ids.len());
+        for contact_id in contact_ids {
+            contacts.push(load_contact_info(context, contact_id).await?);
+        }
+
+        Ok(contacts)
+    }
+
+    pub async fn unblock_contact(&self, context: &Context, contact_id: u32) -> Result<()> {
+        info!("unblocking contact {}", contact_id);
+        Contact::unblock(&context, contact_id)
+            .await
+            .map_err(|err| anyhow!("failed to unblock contact: {}", err))?;
+
+        Ok(())
+    }
+
+    pub async fn rename_contact(
+        &self,
+        context: &Context,
+        contact_id: u32,
+        name: &str,
+    ) -> Result<()> {
+        info!("renaming contact {} to {:?}", contact_id, name);
+        let contact = Contact::load_from_db(context, contact_id)
+            .await
+            .map_err(|err| anyhow!("failed to load contact: {}: {}", contact_id, err))?;
+        // unlike `Contact::create`, this leaves blocked contacts blocked
+        Contact::add_or_lookup(context, name, contact.get_addr(), Origin::ManuallyCreated)
+            .await
+            .map_err(|err| anyhow!("failed to rename contact: {}", err))?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -312,6 +663,147 @@ fn get_timestamp(ts: i64) -> DateTime<Utc> {
     DateTime::from_utc(naive, Utc)
 }
 
+pub async fn load_contact_info(context: &Context, contact_id: u32) -> Result<ContactInfo> {
+    let contact = Contact::load_from_db(context, contact_id)
+        .await
+        .map_err(|err| anyhow!("failed to load contact: {}: {}", contact_id, err))?;
+    let last_seen = match contact.last_seen() {
+        0 => None,
+        ts => Some(get_timestamp(ts)),
+    };
+
+    Ok(ContactInfo {
+        id: contact.id,
+        display_name: contact.get_display_name().to_string(),
+        mail: contact.get_addr().to_string(),
+        profile_image: contact.get_profile_image(context).await?.map(Into::into),
+        color: contact.get_color(),
+        status: contact.get_status().to_string(),
+        last_seen,
+        is_verified: contact.is_verified(context).await? == VerifiedStatus::BidirectVerified,
+        is_blocked: contact.is_blocked(),
+    })
+}
+
+/// The chats whose names match the query, or that have a member whose name or
+/// address matches it.
+async fn matching_chats(context: &Context, query: &str) -> Result<HashSet<ChatId>> {
+    // the core only matches the names of chats
+    let chatlist = Chatlist::try_load(&context, 0, Some(query), None)
+        .await
+        .map_err(|err| anyhow!("failed to search chats: {:?}", err))?;
+    let mut chat_ids: HashSet<_> = (0..chatlist.len())
+        .map(|i| chatlist.get_chat_id(i))
+        .collect();
+
+    let contact_ids = Contact::get_all(&context, 0, Some(query))
+        .await
+        .map_err(|err| anyhow!("failed to search contacts: {:?}", err))?;
+    for contact_id in contact_ids {
+        let chatlist = Chatlist::try_load(&context, 0, None, Some(contact_id))
+            .await
+            .map_err(|err| anyhow!("failed to load chats of {}: {:?}", contact_id, err))?;
+        chat_ids.extend((0..chatlist.len()).map(|i| chatlist.get_chat_id(i)));
+    }
+
+    Ok(chat_ids)
+}
+
+/// The chats of the list matching the search and flags, in the order of the list.
+async fn filter_chats(
+    context: &Context,
+    chatlist: &Chatlist,
+    matches: Option<&HashSet<ChatId>>,
+    flags: ChatListFlags,
+) -> Result<Vec<ChatId>> {
+    // archived chats are listed on their own
+    let all = (0..chatlist.len())
+        .map(|i| chatlist.get_chat_id(i))
+        .filter(|chat_id| !chat_id.is_archived_link())
+        .filter(|chat_id| match matches {
+            Some(matches) => matches.contains(chat_id),
+            None => true,
+        });
+    if !flags.unread_only && !flags.groups_only {
+        return Ok(all.collect());
+    }
+
+    let mut chat_ids = Vec::new();
+    for chat_id in all {
+        if chat_id.is_special() {
+            continue;
+        }
+        let chat = Chat::load_from_db(&context, chat_id).await?;
+
+        if flags.groups_only && chat.get_type() != Chattype::Group {
+            continue;
+        }
+        if flags.unread_only && chat_id.get_fresh_msg_cnt(&context).await? == 0 {
+            continue;
+        }
+
+        chat_ids.push(chat_id);
+    }
+
+    Ok(chat_ids)
+}
+
+/// Builds a message with an optional quote and attachment.
+async fn build_message(
+    context: &Context,
+    text: Option<String>,
+    quote_msg_id: Option<MsgId>,
+    file: Option<Attachment>,
+) -> Result<message::Message> {
+    let typ = match file {
+        Some(ref file) => {
+            deltachat::constants::Viewtype::from_i32(file.typ.to_i32().unwrap()).unwrap()
+        }
+        None => deltachat::constants::Viewtype::Text,
+    };
+    let mut msg = message::Message::new(typ);
+    msg.set_text(text);
+    if let Some(file) = file {
+        msg.set_file(file.path, file.mime.as_deref());
+    }
+
+    if let Some(quote_msg_id) = quote_msg_id {
+        let quote = message::Message::load_from_db(&context, quote_msg_id)
+            .await
+            .map_err(|err| anyhow!("failed to load msg: {}: {}", quote_msg_id, err))?;
+        msg.set_quote(&context, &quote)
+            .await
+            .map_err(|err| anyhow!("failed to quote message: {}", err))?;
+    }
+
+    Ok(msg)
+}
+
+async fn load_draft(context: &Context, chat_id: ChatId) -> Result<Option<Draft>> {
+    let msg = match chat_id.get_draft(&context).await? {
+        Some(msg) => msg,
+        None => return Ok(None),
+    };
+
+    let quote = match msg.quoted_message(&context).await? {
+        Some(quote) => Some(Box::new(
+            load_summary(&context, &mut HashMap::new(), quote).await?,
+        )),
+        None => None,
+    };
+    let file = msg.get_file(&context).map(|path| Attachment {
+        typ: Viewtype::from_i32(msg.get_viewtype().to_i32().unwrap()).unwrap(),
+        path: path.to_string_lossy().into_owned(),
+        mime: msg.get_filemime(),
+    });
+
+    Ok(Some(Draft {
+        text: msg.get_text(),
+        quote,
+        file,
+    }))
+}
+
 async fn load_chat_state(
     context: Context,
     chat_id: ChatId,
@@ -322,10 +814,21 @@ async fn load_chat_state(
             let lot = chats.get_summary(&context, index, Some(&chat)).await?;
 
             let header = lot.get_text1().map(|s| s.to_string()).unwrap_or_default();
-            let preview = lot.get_text2().map(|s| s.to_string()).unwrap_or_default();
+            let mut preview = lot.get_text2().map(|s| s.to_string()).unwrap_or_default();
+
+            let draft = load_draft(&context, chat_id).await?;
+            if draft.is_some() {
+                // the summary shows the draft, if there is one
+                preview = format!("Draft: {}", preview);
+            }
 
             let index = chats.get_index_for_id(chat_id);
             let is_contact_request = chat.is_contact_request();
+            let contacts = chat::get_chat_contacts(&context, chat_id).await?;
+            let contact_id = match chat.get_type() {
+                Chattype::Single => contacts.first().copied(),
+                _ => None,
+            };
 
             (
                 None,
@@ -344,12 +847,14 @@ async fn load_chat_state(
                     color: chat.get_color(&context).await?,
                     is_device_talk: chat.is_device_talk(),
                     is_self_talk: chat.is_self_talk(),
+                    contact_id,
                     fresh_msg_cnt: chat_id.get_fresh_msg_cnt(&context).await?,
-                    member_count: deltachat::chat::get_chat_contacts(&context, chat_id)
-                        .await?
-                        .len(),
+                    member_count: contacts.len(),
                     is_pinned: chat.get_visibility() == ChatVisibility::Pinned,
                     is_archived: chat.get_visibility() == ChatVisibility::Archived,
+                    is_muted: chat.is_muted(),
+                    ephemeral_timer: chat_id.get_ephemeral_timer(&context).await?.to_u32(),
+                    draft,
                 }),
             )
         } else {
@@ -432,7 +937,8 @@ async fn refresh_message_list(
                     }
                 };
                 last_contact_id = Some(msg.get_from_id());
-                last_marker = false;
+                // info messages, e.g. about changed timers, are not part of a conversation
+                last_marker = msg.is_info();
                 let mut inner_msg = InnerChatMessage {
                     id: msg.get_id().to_u32(),
                     from_id: msg.get_from_id(),
@@ -449,10 +955,13 @@ async fn refresh_message_list(
                     file_width: msg.get_width(),
                     file_height: msg.get_height(),
                     is_first,
+                    ephemeral_timer: msg.get_ephemeral_timer().to_u32(),
+                    ephemeral_expires: get_ephemeral_expires(&msg),
                 };
 
                 if let Some(quote) = msg.quoted_message(&context).await? {
-                    inner_msg.quote = Some(load_quote(&context, &mut contacts, quote).await?);
+                    let quote = load_summary(&context, &mut contacts, quote).await?;
+                    inner_msg.quote = Some(Box::new(quote));
                 }
 
                 chat_messages.push(ChatMessage::Message(inner_msg));
@@ -467,11 +976,12 @@ async fn refresh_message_list(
     Ok((chat_id.to_u32(), range, chat_items, chat_messages))
 }
 
-async fn load_quote(
+/// Loads a message on its own, without its quote, e.g. to show it as a quote.
+async fn load_summary(
     context: &Context,
     contacts: &mut HashMap<u32, Contact>,
     msg: message::Message,
-) -> Result<Box<InnerChatMessage>> {
+) -> Result<InnerChatMessage> {
     let from = match contacts.get(&msg.get_from_id()) {
         Some(contact) => contact,
         None => {
@@ -483,7 +993,7 @@ async fn load_quote(
         }
     };
 
-    Ok(Box::new(InnerChatMessage {
+    Ok(InnerChatMessage {
         id: msg.get_id().to_u32(),
         from_id: msg.get_from_id(),
         viewtype: Viewtype::from_i32(msg.get_viewtype().to_i32().unwrap()).unwrap(),
@@ -499,5 +1009,151 @@ async fn load_quote(
         file_width: msg.get_width(),
         file_height: msg.get_height(),
         is_first: true,
-    }))
+        ephemeral_timer: msg.get_ephemeral_timer().to_u32(),
+        ephemeral_expires: get_ephemeral_expires(&msg),
+    })
+}
+
+fn get_ephemeral_expires(msg: &message::Message) -> Option<DateTime<Utc>> {
+    match msg.get_ephemeral_timestamp() {
+        0 => None,
+        ts => Some(get_timestamp(ts)),
+    }
+}
+
+/// The text around the first match of `query`, so that results show why they matched.
+fn snippet(text: &str, query: &str) -> String {
+    const CONTEXT_CHARS: usize = 30;
+
+    // matching on the text itself, lowercasing changes the length of some chars
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let start = text
+        .char_indices()
+        .position(|(pos, _)| {
+            let mut rest = text[pos..].chars().flat_map(char::to_lowercase);
+            query.iter().all(|c| rest.next() == Some(*c))
+        })
+        .unwrap_or_default();
+
+    let skip = start.saturating_sub(CONTEXT_CHARS);
+    let take = CONTEXT_CHARS * 2 + query.len();
+    let mut snippet: String = text.chars().skip(skip).take(take).collect();
+    if skip > 0 {
+        snippet.insert(0, '…');
+    }
+    if skip + take < text.chars().count() {
+        snippet.push('…');
+    }
+
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+    use deltachat::chat::ProtectionStatus;
+
+    #[test]
+    fn test_rename_blocked_contact() {
+        task::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let context = Context::new("test".into(), dir.path().join("db.sqlite").into(), 1)
+                .await
+                .unwrap();
+            let contact_id = Contact::create(&context, "Bob", "bob@example.org")
+                .await
+                .unwrap();
+            Contact::block(&context, contact_id).await.unwrap();
+
+            let account = Account::new().unwrap();
+            account
+                .rename_contact(&context, contact_id, "Robert")
+                .await
+                .unwrap();
+
+            let contact = Contact::load_from_db(&context, contact_id).await.unwrap();
+            assert_eq!(contact.get_display_name(), "Robert");
+            assert!(contact.is_blocked());
+        });
+    }
+
+    #[test]
+    fn test_select_archived_chat() {
+        task::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let context = Context::new("test".into(), dir.path().join("db.sqlite").into(), 1)
+                .await
+                .unwrap();
+            let chat_id = chat::create_group_chat(&context, ProtectionStatus::Unprotected, "Group")
+                .await
+                .unwrap();
+            chat_id
+                .set_visibility(&context, ChatVisibility::Archived)
+                .await
+                .unwrap();
+
+            let account = Account::new().unwrap();
+            let chat = account.select_chat(&context, chat_id).await.unwrap();
+            let chat = chat.expect("archived chats can be selected");
+            assert_eq!(chat.id, chat_id.to_u32());
+            assert!(chat.is_archived);
+        });
+    }
+
+    #[test]
+    fn test_search_chat_members() {
+        task::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let context = Context::new("test".into(), dir.path().join("db.sqlite").into(), 1)
+                .await
+                .unwrap();
+            let contact_id = Contact::create(&context, "Bob", "bob@example.org")
+                .await
+                .unwrap();
+            let friends =
+                chat::create_group_chat(&context, ProtectionStatus::Unprotected, "Friends")
+                    .await
+                    .unwrap();
+            assert!(chat::add_contact_to_chat(&context, friends, contact_id).await);
+            chat::create_group_chat(&context, ProtectionStatus::Unprotected, "Others")
+                .await
+                .unwrap();
+
+            let account = Account::new().unwrap();
+            for query in &["bob", "example.org"] {
+                let (_, len, _, chats) = account
+                    .load_chat_list(&context, 0, 10, Some(*query), ChatListFlags::default())
+                    .await
+                    .unwrap();
+                assert_eq!(len, 1);
+                assert_eq!(chats[0].id, friends.to_u32());
+            }
+        });
+    }
+
+    #[test]
+    fn test_snippet() {
+        assert_eq!(snippet("Hello World", "world"), "Hello World");
+        // without a match the start is shown
+        let text = "a".repeat(100);
+        assert_eq!(snippet(&text, "b"), format!("{}…", "a".repeat(61)));
+    }
+
+    #[test]
+    fn test_snippet_multibyte() {
+        let text = format!("{}needle{}", "ä".repeat(50), "ö".repeat(50));
+        assert_eq!(
+            snippet(&text, "NEEDLE"),
+            format!("…{}needle{}…", "ä".repeat(30), "ö".repeat(30))
+        );
+
+        // 'İ' lowercases to two chars
+        let text = format!("{}needle", "İ".repeat(50));
+        assert_eq!(
+            snippet(&text, "needle"),
+            format!("…{}needle", "İ".repeat(30))
+        );
+        assert_eq!(snippet("İstanbul", "i̇stan"), "İstanbul");
+    }
 }
@@ -0,0 +1,103 @@
+use async_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use async_tungstenite::tungstenite::http::StatusCode;
+use log::warn;
+use rand::{distributions::Alphanumeric, Rng};
+
+/// Name of the query parameter the secret is passed in, e.g. `ws://localhost:8081/?token=..`.
+pub const TOKEN_PARAM: &str = "token";
+
+/// Per launch secret, which WebSocket clients have to present to be served.
+#[derive(Debug, Clone)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn generate() -> Self {
+        let secret = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        Secret(secret)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Compares in constant time, to not leak the secret through timing.
+    pub fn verify(&self, candidate: &str) -> bool {
+        let a = self.0.as_bytes();
+        let b = candidate.as_bytes();
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// Checks the token of an incoming WebSocket handshake, rejecting it if it doesn't match.
+    pub fn check_request(
+        &self,
+        request: &Request,
+        response: Response,
+    ) -> Result<Response, ErrorResponse> {
+        let token = request.uri().query().and_then(|query| {
+            query.split('&').find_map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(TOKEN_PARAM), Some(value)) => Some(value),
+                    _ => None,
+                }
+            })
+        });
+
+        match token {
+            Some(token) if self.verify(token) => Ok(response),
+            _ => {
+                warn!("rejecting unauthenticated connection");
+                let mut response = ErrorResponse::new(Some("invalid token".into()));
+                *response.status_mut() = StatusCode::UNAUTHORIZED;
+                Err(response)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(secret: &Secret, uri: &str) -> Result<Response, ErrorResponse> {
+        let request = Request::builder().uri(uri).body(()).unwrap();
+        secret.check_request(&request, Response::default())
+    }
+
+    #[test]
+    fn test_verify() {
+        let secret = Secret("secret".to_string());
+        assert!(secret.verify("secret"));
+        assert!(!secret.verify("secreT"));
+        assert!(!secret.verify("secre"));
+        assert!(!secret.verify("secrets"));
+        assert!(!secret.verify(""));
+    }
+
+    #[test]
+    fn test_check_request() {
+        let secret = Secret::generate();
+        assert_eq!(secret.as_str().len(), 32);
+
+        let uri = format!("ws://localhost:8081/?{}={}", TOKEN_PARAM, secret.as_str());
+        assert!(check(&secret, &uri).is_ok());
+        let uri = format!(
+            "ws://localhost:8081/?a=b&{}={}",
+            TOKEN_PARAM,
+            secret.as_str()
+        );
+        assert!(check(&secret, &uri).is_ok());
+
+        let wrong = format!("ws://localhost:8081/?{}=wrong", TOKEN_PARAM);
+        let empty = format!("ws://localhost:8081/?{}=", TOKEN_PARAM);
+        let other = format!("ws://localhost:8081/?other={}", secret.as_str());
+        for uri in &["ws://localhost:8081/", &wrong, &empty, &other] {
+            let response = check(&secret, uri).unwrap_err();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Runs the backend without a window, serving the web frontend to ordinary browsers.
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result};
+use async_std::task;
+use dc40_backend::{
+    auth::{Secret, TOKEN_PARAM},
+    listen::{option, ListenAddr, Listener},
+    server,
+    state::LocalState,
+    web,
+};
+use log::*;
+
+const DEFAULT_HTTP_PORT: u16 = 8080;
+
+fn main() {
+    femme::with_level(log::LevelFilter::Info);
+
+    if let Err(err) = task::block_on(run()) {
+        error!("{:#}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run() -> Result<()> {
+    let local_state = LocalState::new()
+        .await
+        .context("Can't restore local state")?;
+    let secret = Secret::generate();
+
+    let addr = ListenAddr::from_env()?;
+    let listener = Listener::bind(&addr).await?;
+    let ws_addr = listener
+        .local_addr()
+        .context("browsers can only connect to the backend through TCP")?;
+    info!("Listening on: {}", ws_addr);
+
+    let http_port = match option("http-port", "DC40_HTTP_PORT")? {
+        Some(port) => port
+            .parse()
+            .with_context(|| format!("invalid http port: {}", port))?,
+        None => DEFAULT_HTTP_PORT,
+    };
+    let http_addr = SocketAddr::new(ws_addr.ip(), http_port);
+    let dist: PathBuf = option("dist", "DC40_DIST")?
+        .unwrap_or_else(|| "dist".into())
+        .into();
+
+    let mut display_addr = http_addr;
+    if display_addr.ip().is_unspecified() {
+        display_addr.set_ip(Ipv4Addr::LOCALHOST.into());
+    }
+    println!(
+        "Open http://{}/#{}={} in your browser",
+        display_addr,
+        TOKEN_PARAM,
+        secret.as_str()
+    );
+
+    task::spawn(server::serve(listener, local_state.clone(), secret.clone()));
+    web::serve_web(http_addr, dist, ws_addr.port(), secret, local_state).await
+}
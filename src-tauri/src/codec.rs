@@ -0,0 +1,492 @@
+//! Wire formats of the WebSocket backend.
+//!
+//! Besides bincode encoded [`RequestEnvelope`]s in binary frames, clients can speak
+//! JSON-RPC 2.0 in text frames. Methods are named after the variants of [`Request`] and
+//! take its fields as named params (or a params array for tuple variants), e.g.
+//!
+//! ```json
+//! {"jsonrpc": "2.0", "id": 1, "method": "SelectChat", "params": {"account": 1, "chat_id": 10}}
+//! ```
+//!
+//! The result of a call is the list of [`Response`]s it produced, [`shared::Event`]s are
+//! sent as `event` notifications and [`Response::Resync`] as `resync` notification.
+//! Other responses the backend sends on its own, outside of a call, arrive as `update`
+//! notifications. Request ids are unsigned integers or strings, requests without an id
+//! are notifications: they are executed but never answered, not even with an error.
+//!
+//! Errors use the codes of the spec for requests that could not be decoded, and -32000
+//! for requests that failed, with the [`ErrorKind`] in their data.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, ensure, Context as _, Result};
+use async_tungstenite::tungstenite::Message;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use shared::{ErrorKind, Request, RequestEnvelope, RequestMethod, Response};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Bincode,
+    JsonRpc,
+}
+
+impl Protocol {
+    /// Clients pick the protocol with the frame type of their first message.
+    pub fn detect(msg: &Message) -> Option<Self> {
+        match msg {
+            Message::Binary(_) => Some(Protocol::Bincode),
+            Message::Text(_) => Some(Protocol::JsonRpc),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded request, without an id if it is a JSON-RPC notification.
+#[derive(Debug)]
+pub struct Incoming {
+    pub request_id: Option<u32>,
+    pub request: Request,
+}
+
+/// A request that could not be decoded, with its id if it got that far.
+#[derive(Debug)]
+pub struct DecodeError {
+    pub request_id: Option<u32>,
+    /// JSON-RPC notifications are not answered, even if they are invalid.
+    pub is_notification: bool,
+    pub kind: ErrorKind,
+    pub error: anyhow::Error,
+}
+
+/// Decodes a message, JSON-RPC calls are registered with `in_flight` to be answered.
+pub fn decode(
+    protocol: Protocol,
+    msg: Message,
+    in_flight: &InFlight,
+) -> Result<Incoming, DecodeError> {
+    match protocol {
+        Protocol::Bincode => bincode::deserialize(&msg.into_data())
+            .map(
+                |RequestEnvelope {
+                     request_id,
+                     request,
+                 }| Incoming {
+                    request_id: Some(request_id),
+                    request,
+                },
+            )
+            .map_err(|err| DecodeError {
+                request_id: None,
+                is_notification: false,
+                kind: ErrorKind::InvalidRequest,
+                error: err.into(),
+            }),
+        Protocol::JsonRpc => decode_json_rpc(msg, in_flight),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRpcRequest {
+    jsonrpc: String,
+    id: Option<CallId>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CallId {
+    Number(u32),
+    String(String),
+}
+
+fn decode_json_rpc(msg: Message, in_flight: &InFlight) -> Result<Incoming, DecodeError> {
+    let invalid = |kind, error| DecodeError {
+        request_id: None,
+        is_notification: false,
+        kind,
+        error,
+    };
+    let value: Value = msg
+        .to_text()
+        .map_err(Into::into)
+        .and_then(|text| serde_json::from_str(text).map_err(Into::into))
+        .map_err(|error| invalid(ErrorKind::ParseError, error))?;
+    let parse = |value: Value| -> Result<JsonRpcRequest> {
+        let req: JsonRpcRequest = serde_json::from_value(value)?;
+        ensure!(
+            req.jsonrpc == "2.0",
+            "unsupported jsonrpc version {}",
+            req.jsonrpc
+        );
+        Ok(req)
+    };
+    let JsonRpcRequest {
+        id, method, params, ..
+    } = parse(value).map_err(|error| invalid(ErrorKind::InvalidRequest, error))?;
+
+    let request_id = id.map(|id| in_flight.register(id));
+    let decode = || -> Result<Request, (ErrorKind, anyhow::Error)> {
+        if serde_json::from_value::<RequestMethod>(Value::String(method.clone())).is_err() {
+            let error = anyhow!("unknown method {}", method);
+            return Err((ErrorKind::UnknownMethod, error));
+        }
+
+        // the externally tagged serde representation of `Request`
+        let request = match params {
+            Value::Null => Value::String(method),
+            params => {
+                let mut map = Map::new();
+                map.insert(method, params);
+                Value::Object(map)
+            }
+        };
+        serde_json::from_value::<Request>(request)
+            .context("invalid params")
+            .map_err(|error| (ErrorKind::InvalidParams, error))
+    };
+
+    match decode() {
+        Ok(request) => Ok(Incoming {
+            request_id,
+            request,
+        }),
+        Err((kind, error)) => Err(DecodeError {
+            request_id,
+            is_notification: request_id.is_none(),
+            kind,
+            error,
+        }),
+    }
+}
+
+/// The JSON-RPC calls of a connection, shared by it and its [`Encoder`].
+///
+/// Responses are only collected as result while a call is in flight, everything else
+/// the backend sends is pushed as notification.
+#[derive(Debug, Clone, Default)]
+pub struct InFlight(Arc<Mutex<Calls>>);
+
+#[derive(Debug, Default)]
+struct Calls {
+    /// The request id of the call in progress.
+    current: Option<u32>,
+    /// The ids of calls that are strings, by the request id they are processed with.
+    string_ids: HashMap<u32, String>,
+    next_string_id: u32,
+}
+
+impl InFlight {
+    /// Starts collecting the results of the call, until it is acked or fails.
+    pub fn start(&self, request_id: u32) {
+        self.0.lock().unwrap().current = Some(request_id);
+    }
+
+    fn is_active(&self) -> bool {
+        self.0.lock().unwrap().current.is_some()
+    }
+
+    /// Ends the call if it is the one in flight.
+    fn finish(&self, request_id: u32) -> bool {
+        let mut calls = self.0.lock().unwrap();
+        if calls.current == Some(request_id) {
+            calls.current = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The request id to process the call with.
+    fn register(&self, id: CallId) -> u32 {
+        match id {
+            CallId::Number(request_id) => request_id,
+            CallId::String(id) => {
+                let mut calls = self.0.lock().unwrap();
+                // counting down, away from the numbers clients use
+                let request_id = u32::MAX - calls.next_string_id;
+                calls.next_string_id = calls.next_string_id.wrapping_add(1);
+                calls.string_ids.insert(request_id, id);
+                request_id
+            }
+        }
+    }
+
+    /// The id the client gave the call, which it is answered with.
+    fn answer(&self, request_id: u32) -> Value {
+        match self.0.lock().unwrap().string_ids.remove(&request_id) {
+            Some(id) => Value::String(id),
+            None => Value::from(request_id),
+        }
+    }
+}
+
+/// Turns the [`Response`]s of a connection into messages of its [`Protocol`].
+#[derive(Debug)]
+pub struct Encoder {
+    protocol: Protocol,
+    in_flight: InFlight,
+    /// Responses of the JSON-RPC call in progress.
+    results: Vec<Response>,
+}
+
+impl Encoder {
+    pub fn new(protocol: Protocol) -> Self {
+        Encoder {
+            protocol,
+            in_flight: InFlight::default(),
+            results: Vec::new(),
+        }
+    }
+
+    /// The handle to tell the encoder which call is being processed.
+    pub fn in_flight(&self) -> InFlight {
+        self.in_flight.clone()
+    }
+
+    pub fn encode(&mut self, response: Response) -> Vec<Message> {
+        match self.protocol {
+            Protocol::Bincode => vec![Message::binary(bincode::serialize(&response).unwrap())],
+            Protocol::JsonRpc => self
+                .encode_json_rpc(response)
+                .map(|value| Message::text(value.to_string()))
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn encode_json_rpc(&mut self, response: Response) -> Option<Value> {
+        match response {
+            Response::Event {
+                seq,
+                account,
+                event,
+            } => Some(json!({
+                "jsonrpc": "2.0",
+                "method": "event",
+                "params": { "seq": seq, "account": account, "event": event },
+            })),
+            Response::Resync => Some(json!({
+                "jsonrpc": "2.0",
+                "method": "resync",
+            })),
+            Response::Ack { request_id } => {
+                let results = if self.in_flight.finish(request_id) {
+                    std::mem::take(&mut self.results)
+                } else {
+                    Vec::new()
+                };
+                Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": self.in_flight.answer(request_id),
+                    "result": results,
+                }))
+            }
+            Response::Error {
+                request_id,
+                kind,
+                message,
+            } => {
+                // errors of requests that could not be decoded leave the call in flight
+                if matches!(request_id, Some(request_id) if self.in_flight.finish(request_id)) {
+                    self.results.clear();
+                }
+                let code = match kind {
+                    ErrorKind::ParseError => -32700,
+                    ErrorKind::InvalidRequest => -32600,
+                    ErrorKind::UnknownMethod => -32601,
+                    ErrorKind::InvalidParams => -32602,
+                    _ => -32000,
+                };
+                Some(json!({
+                    "jsonrpc": "2.0",
+                    "id": request_id.map(|request_id| self.in_flight.answer(request_id)),
+                    "error": { "code": code, "message": message, "data": { "kind": kind } },
+                }))
+            }
+            response if self.in_flight.is_active() => {
+                self.results.push(response);
+                None
+            }
+            response => Some(json!({
+                "jsonrpc": "2.0",
+                "method": "update",
+                "params": response,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_text(text: &str) -> Result<Incoming, DecodeError> {
+        decode(Protocol::JsonRpc, Message::text(text), &InFlight::default())
+    }
+
+    fn encode_text(encoder: &mut Encoder, response: Response) -> Vec<Value> {
+        encoder
+            .encode(response)
+            .into_iter()
+            .map(|msg| serde_json::from_str(msg.to_text().unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_params() {
+        let incoming = decode_text(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "SelectChat", "params": {"account": 2, "chat_id": 10}}"#,
+        )
+        .unwrap();
+        assert_eq!(incoming.request_id, Some(1));
+        assert!(matches!(
+            incoming.request,
+            Request::SelectChat {
+                account: 2,
+                chat_id: 10
+            }
+        ));
+
+        // unit variants take no params
+        let incoming =
+            decode_text(r#"{"jsonrpc": "2.0", "id": 2, "method": "MaybeNetwork"}"#).unwrap();
+        assert!(matches!(incoming.request, Request::MaybeNetwork));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        let err = decode_text(r#"{"jsonrpc": "2.0", "id": 3, "method": "Nope"}"#).unwrap_err();
+        assert_eq!(err.request_id, Some(3));
+        assert_eq!(err.kind, ErrorKind::UnknownMethod);
+        assert!(!err.is_notification);
+
+        let err = decode_text(
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "SelectChat", "params": {"account": 2}}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err.request_id, Some(4));
+        assert_eq!(err.kind, ErrorKind::InvalidParams);
+
+        let err =
+            decode_text(r#"{"jsonrpc": "1.0", "id": 5, "method": "MaybeNetwork"}"#).unwrap_err();
+        assert_eq!(err.request_id, None);
+        assert_eq!(err.kind, ErrorKind::InvalidRequest);
+        assert!(!err.is_notification);
+
+        let err = decode_text("not json").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ParseError);
+        assert!(!err.is_notification);
+    }
+
+    #[test]
+    fn test_string_id() {
+        let mut encoder = Encoder::new(Protocol::JsonRpc);
+        let in_flight = encoder.in_flight();
+        let text = r#"{"jsonrpc": "2.0", "id": "a", "method": "MaybeNetwork"}"#;
+        let incoming = decode(Protocol::JsonRpc, Message::text(text), &in_flight).unwrap();
+        let request_id = incoming.request_id.unwrap();
+        in_flight.start(request_id);
+
+        let messages = encode_text(&mut encoder, Response::Ack { request_id });
+        assert_eq!(
+            messages,
+            vec![json!({"jsonrpc": "2.0", "id": "a", "result": []})]
+        );
+
+        let text = r#"{"jsonrpc": "2.0", "id": "b", "method": "Nope"}"#;
+        let err = decode(Protocol::JsonRpc, Message::text(text), &in_flight).unwrap_err();
+        let messages = encode_text(
+            &mut encoder,
+            Response::Error {
+                request_id: err.request_id,
+                kind: err.kind,
+                message: "unknown".to_string(),
+            },
+        );
+        assert_eq!(messages[0]["id"], "b");
+        assert_eq!(messages[0]["error"]["code"], -32601);
+    }
+
+    #[test]
+    fn test_decode_notification() {
+        let incoming = decode_text(
+            r#"{"jsonrpc": "2.0", "method": "SelectAccount", "params": {"account": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(incoming.request_id, None);
+        assert!(matches!(
+            incoming.request,
+            Request::SelectAccount { account: 1 }
+        ));
+
+        let err = decode_text(r#"{"jsonrpc": "2.0", "method": "Nope"}"#).unwrap_err();
+        assert!(err.is_notification);
+    }
+
+    #[test]
+    fn test_encode_call() {
+        let mut encoder = Encoder::new(Protocol::JsonRpc);
+        encoder.in_flight().start(7);
+
+        assert!(encode_text(&mut encoder, Response::Contacts(Vec::new())).is_empty());
+        let messages = encode_text(&mut encoder, Response::Ack { request_id: 7 });
+        assert_eq!(
+            messages,
+            vec![json!({"jsonrpc": "2.0", "id": 7, "result": [{"Contacts": []}]})]
+        );
+
+        // the call is over, later responses are pushed
+        let messages = encode_text(&mut encoder, Response::Contacts(Vec::new()));
+        assert_eq!(
+            messages,
+            vec![json!({"jsonrpc": "2.0", "method": "update", "params": {"Contacts": []}})]
+        );
+    }
+
+    #[test]
+    fn test_encode_error() {
+        let mut encoder = Encoder::new(Protocol::JsonRpc);
+        encoder.in_flight().start(8);
+        encode_text(&mut encoder, Response::Contacts(Vec::new()));
+
+        // an undecodable request does not end the call in flight
+        let messages = encode_text(
+            &mut encoder,
+            Response::Error {
+                request_id: None,
+                kind: ErrorKind::ParseError,
+                message: "invalid".to_string(),
+            },
+        );
+        assert_eq!(messages[0]["id"], Value::Null);
+        assert_eq!(messages[0]["error"]["code"], -32700);
+
+        let messages = encode_text(
+            &mut encoder,
+            Response::Error {
+                request_id: Some(10),
+                kind: ErrorKind::InvalidParams,
+                message: "invalid".to_string(),
+            },
+        );
+        assert_eq!(messages[0]["error"]["code"], -32602);
+
+        let messages = encode_text(
+            &mut encoder,
+            Response::Error {
+                request_id: Some(8),
+                kind: ErrorKind::Other,
+                message: "failed".to_string(),
+            },
+        );
+        assert_eq!(messages[0]["id"], 8);
+        assert_eq!(messages[0]["error"]["code"], -32000);
+
+        let messages = encode_text(&mut encoder, Response::Ack { request_id: 9 });
+        assert_eq!(messages[0]["result"], json!([]));
+    }
+}
@@ -2,8 +2,29 @@ use async_std::task;
 use log::info;
 use tauri::{api::dialog::FileDialogBuilder, command, State};
 
+use crate::auth::Secret;
+use crate::listen::BackendUrl;
 use crate::state::LocalState;
 
+#[command]
+pub fn backend_secret(secret: State<'_, Secret>) -> String {
+    secret.as_str().to_string()
+}
+
+#[command]
+pub fn backend_url(url: State<'_, BackendUrl>) -> Result<String, String> {
+    url.get().map(Into::into).map_err(|e| e.to_string())
+}
+
+/// Lets the user pick an image, returns its path.
+#[command]
+pub fn pick_image() -> Option<String> {
+    FileDialogBuilder::new()
+        .add_filter("Images", &["png", "jpg", "jpeg", "gif", "webp"])
+        .pick_file()
+        .map(|path| path.to_string_lossy().into_owned())
+}
+
 #[command]
 pub fn load_backup(local_state: State<'_, LocalState>) -> Result<String, String> {
     if let Some(path) = FileDialogBuilder::new().pick_file() {
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use shared::ErrorKind;
+
+/// An error tagged with an [`ErrorKind`], so it can be reported to the client.
+#[derive(Debug)]
+pub struct RequestError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl RequestError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        RequestError {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+pub fn invalid_account(account_id: u32) -> anyhow::Error {
+    RequestError::new(
+        ErrorKind::InvalidAccount,
+        format!("invalid account: {}", account_id),
+    )
+    .into()
+}
+
+pub fn no_account_selected() -> anyhow::Error {
+    RequestError::new(ErrorKind::NoAccountSelected, "no account selected").into()
+}
+
+pub fn no_chat_selected() -> anyhow::Error {
+    RequestError::new(ErrorKind::NoChatSelected, "no chat selected").into()
+}
+
+/// Returns the kind of the first [`RequestError`] in the chain, or
+/// [`ErrorKind::Other`].
+pub fn error_kind(err: &anyhow::Error) -> ErrorKind {
+    err.chain()
+        .find_map(|err| err.downcast_ref::<RequestError>())
+        .map(|err| err.kind)
+        .unwrap_or(ErrorKind::Other)
+}
@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use shared::Event;
+
+/// How many events are kept around for clients that reconnect.
+const HISTORY_LEN: usize = 1024;
+
+/// An event as it is sent to clients, numbered across all accounts.
+#[derive(Debug, Clone)]
+pub struct RecordedEvent {
+    pub seq: u64,
+    pub account: u32,
+    pub event: Event,
+}
+
+/// The most recent events, so that reconnecting clients can catch up.
+#[derive(Debug)]
+pub struct History {
+    /// Identifies this run of the backend, sequence numbers start anew with every run.
+    run_id: u64,
+    last_seq: u64,
+    /// The newest event that is not kept anymore, `0` if none was dropped yet.
+    dropped_seq: u64,
+    events: VecDeque<RecordedEvent>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            run_id: rand::random(),
+            last_seq: 0,
+            dropped_seq: 0,
+            events: VecDeque::new(),
+        }
+    }
+}
+
+impl History {
+    /// Numbers the event and remembers it. Sequence numbers start at `1`.
+    ///
+    /// Log events are numbered but not kept, they are of no use to clients catching up.
+    pub fn record(&mut self, account: u32, event: Event) -> RecordedEvent {
+        self.last_seq += 1;
+        let event = RecordedEvent {
+            seq: self.last_seq,
+            account,
+            event,
+        };
+
+        if !matches!(event.event, Event::Log(_)) {
+            if self.events.len() == HISTORY_LEN {
+                if let Some(dropped) = self.events.pop_front() {
+                    self.dropped_seq = dropped.seq;
+                }
+            }
+            self.events.push_back(event.clone());
+        }
+
+        event
+    }
+
+    pub fn run_id(&self) -> u64 {
+        self.run_id
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// The events after `last_seq` of the run `run_id`, or `None` if some of them are
+    /// not kept anymore.
+    pub fn since(&self, run_id: u64, last_seq: u64) -> Option<Vec<RecordedEvent>> {
+        if run_id != self.run_id || last_seq > self.last_seq {
+            // numbered by an earlier run of the backend
+            return None;
+        }
+        if last_seq < self.dropped_seq {
+            return None;
+        }
+
+        Some(
+            self.events
+                .iter()
+                .filter(|event| event.seq > last_seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed(chat_id: u32) -> Event {
+        Event::MessagesChanged { chat_id }
+    }
+
+    fn seqs(events: Option<Vec<RecordedEvent>>) -> Option<Vec<u64>> {
+        events.map(|events| events.iter().map(|event| event.seq).collect())
+    }
+
+    #[test]
+    fn test_since() {
+        let mut history = History::default();
+        let run_id = history.run_id();
+        assert_eq!(seqs(history.since(run_id, 0)), Some(vec![]));
+
+        for chat_id in 0..3 {
+            history.record(1, changed(chat_id));
+        }
+        assert_eq!(seqs(history.since(run_id, 0)), Some(vec![1, 2, 3]));
+        assert_eq!(seqs(history.since(run_id, 2)), Some(vec![3]));
+        assert_eq!(seqs(history.since(run_id, 3)), Some(vec![]));
+        assert_eq!(seqs(history.since(run_id, 4)), None);
+    }
+
+    #[test]
+    fn test_since_other_run() {
+        let mut history = History::default();
+        for chat_id in 0..10 {
+            history.record(1, changed(chat_id));
+        }
+
+        let other_run = history.run_id().wrapping_add(1);
+        assert_eq!(seqs(history.since(other_run, 5)), None);
+    }
+
+    #[test]
+    fn test_since_ring_boundary() {
+        let mut history = History::default();
+        let run_id = history.run_id();
+        for chat_id in 0..HISTORY_LEN as u32 {
+            history.record(1, changed(chat_id));
+        }
+        // everything is still kept
+        assert_eq!(
+            history.since(run_id, 0).map(|events| events.len()),
+            Some(HISTORY_LEN)
+        );
+
+        history.record(1, changed(0));
+        // the first event is gone
+        assert_eq!(seqs(history.since(run_id, 0)), None);
+        assert_eq!(
+            history.since(run_id, 1).map(|events| events.len()),
+            Some(HISTORY_LEN)
+        );
+        assert_eq!(
+            seqs(history.since(run_id, HISTORY_LEN as u64)),
+            Some(vec![HISTORY_LEN as u64 + 1])
+        );
+    }
+
+    #[test]
+    fn test_logs_are_not_kept() {
+        let mut history = History::default();
+        let run_id = history.run_id();
+        history.record(1, changed(0));
+        for _ in 0..2 * HISTORY_LEN {
+            let event = history.record(1, Event::Log(shared::Log::Info("log".to_string())));
+            assert!(event.seq > 1);
+        }
+        history.record(1, changed(1));
+
+        let last_seq = 2 * HISTORY_LEN as u64 + 2;
+        assert_eq!(history.last_seq(), last_seq);
+        assert_eq!(seqs(history.since(run_id, 0)), Some(vec![1, last_seq]));
+        assert_eq!(seqs(history.since(run_id, 5)), Some(vec![last_seq]));
+    }
+}
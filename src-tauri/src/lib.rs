@@ -1,5 +1,14 @@
 extern crate static_assertions as sa;
 
 pub mod account;
+pub mod auth;
+pub mod codec;
 pub mod commands;
+pub mod error;
+pub mod history;
+pub mod listen;
+pub mod queue;
+pub mod server;
+pub mod session;
 pub mod state;
+pub mod web;
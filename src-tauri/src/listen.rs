@@ -0,0 +1,251 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context as _, Result};
+use async_std::net::TcpListener;
+#[cfg(unix)]
+use async_std::os::unix::net::UnixListener;
+
+pub const DEFAULT_PORT: u16 = 8081;
+
+/// Looks up `--<name> <value>` or `--<name>=<value>` on the command line, falling back to
+/// the environment variable `env`. Unknown arguments are ignored, the last value given wins.
+pub fn option(name: &str, env: &str) -> Result<Option<String>> {
+    let flag = format!("--{}", name);
+
+    let mut value = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            let next = args
+                .next()
+                .with_context(|| format!("missing value for {}", flag))?;
+            value = Some(next);
+        } else if let Some(next) = arg.strip_prefix(&flag).and_then(|s| s.strip_prefix('=')) {
+            value = Some(next.to_string());
+        }
+    }
+
+    Ok(value.or_else(|| std::env::var(env).ok()))
+}
+
+/// Where the WebSocket backend listens.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl ListenAddr {
+    /// Reads the address from the command line (`--bind <ip>`, `--port <port>`,
+    /// `--socket <path>`), falling back to `DC40_BIND`, `DC40_PORT` and `DC40_SOCKET`.
+    ///
+    /// Defaults to `127.0.0.1:8081`, port `0` lets the OS pick a free port.
+    pub fn from_env() -> Result<Self> {
+        Self::from_parts(
+            option("bind", "DC40_BIND")?,
+            option("port", "DC40_PORT")?,
+            option("socket", "DC40_SOCKET")?,
+        )
+    }
+
+    fn from_parts(
+        bind: Option<String>,
+        port: Option<String>,
+        socket: Option<String>,
+    ) -> Result<Self> {
+        if let Some(socket) = socket {
+            ensure!(
+                bind.is_none() && port.is_none(),
+                "a socket path can not be combined with a bind address or port"
+            );
+            #[cfg(unix)]
+            return Ok(ListenAddr::Unix(socket.into()));
+            #[cfg(not(unix))]
+            bail!(
+                "unix sockets are not supported on this platform: {}",
+                socket
+            );
+        }
+
+        let ip = match bind {
+            Some(bind) => bind
+                .parse::<IpAddr>()
+                .with_context(|| format!("invalid bind address: {}", bind))?,
+            None => Ipv4Addr::LOCALHOST.into(),
+        };
+        let port = match port {
+            Some(port) => port
+                .parse::<u16>()
+                .with_context(|| format!("invalid port: {}", port))?,
+            None => DEFAULT_PORT,
+        };
+
+        Ok(ListenAddr::Tcp(SocketAddr::new(ip, port)))
+    }
+}
+
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    pub async fn bind(addr: &ListenAddr) -> Result<Self> {
+        match addr {
+            ListenAddr::Tcp(addr) => {
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("failed to bind {}", addr))?;
+                Ok(Listener::Tcp(listener))
+            }
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                remove_stale_socket(path)?;
+                let listener = UnixListener::bind(path)
+                    .await
+                    .with_context(|| format!("failed to bind {}", path.display()))?;
+                Ok(Listener::Unix(listener))
+            }
+        }
+    }
+
+    /// The bound address, if listening on TCP.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix(_) => None,
+        }
+    }
+
+    /// The WebSocket URL clients can connect to, if listening on TCP.
+    pub fn url(&self) -> Option<String> {
+        let mut addr = self.local_addr()?;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(Ipv4Addr::LOCALHOST.into());
+        }
+        Some(format!("ws://{}/", addr))
+    }
+}
+
+/// Removes the socket of a previous run, which nothing listens on anymore.
+///
+/// Anything else at the path is left alone and fails the bind.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> Result<()> {
+    use std::io::ErrorKind;
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixStream;
+
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to inspect {}", path.display()))
+        }
+    };
+    ensure!(
+        metadata.file_type().is_socket(),
+        "{} exists and is not a socket",
+        path.display()
+    );
+
+    match UnixStream::connect(path) {
+        Ok(_) => bail!("{} is in use", path.display()),
+        Err(err) if err.kind() == ErrorKind::ConnectionRefused => std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove {}", path.display())),
+        Err(err) => Err(err).with_context(|| format!("failed to connect to {}", path.display())),
+    }
+}
+
+/// The URL of the WebSocket backend, handed to the frontend.
+#[derive(Debug, Clone)]
+pub struct BackendUrl(pub Option<String>);
+
+impl BackendUrl {
+    pub fn get(&self) -> Result<&str> {
+        match self.0 {
+            Some(ref url) => Ok(url),
+            None => bail!("the backend is not reachable through TCP"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_parts(
+        bind: Option<&str>,
+        port: Option<&str>,
+        socket: Option<&str>,
+    ) -> Result<ListenAddr> {
+        ListenAddr::from_parts(
+            bind.map(Into::into),
+            port.map(Into::into),
+            socket.map(Into::into),
+        )
+    }
+
+    #[test]
+    fn test_from_parts_tcp() {
+        assert_eq!(
+            from_parts(None, None, None).unwrap(),
+            ListenAddr::Tcp(SocketAddr::new(Ipv4Addr::LOCALHOST.into(), DEFAULT_PORT))
+        );
+        assert_eq!(
+            from_parts(Some("0.0.0.0"), Some("0"), None).unwrap(),
+            ListenAddr::Tcp("0.0.0.0:0".parse().unwrap())
+        );
+        assert_eq!(
+            from_parts(Some("::1"), Some("9000"), None).unwrap(),
+            ListenAddr::Tcp("[::1]:9000".parse().unwrap())
+        );
+
+        assert!(from_parts(Some("localhost"), None, None).is_err());
+        assert!(from_parts(None, Some("70000"), None).is_err());
+        assert!(from_parts(None, Some("http"), None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_from_parts_unix() {
+        assert_eq!(
+            from_parts(None, None, Some("/tmp/dc40.sock")).unwrap(),
+            ListenAddr::Unix("/tmp/dc40.sock".into())
+        );
+
+        // the socket replaces the TCP address
+        assert!(from_parts(Some("127.0.0.1"), None, Some("/tmp/dc40.sock")).is_err());
+        assert!(from_parts(None, Some("8081"), Some("/tmp/dc40.sock")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bind_unix() {
+        async_std::task::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+
+            // a file is not mistaken for a left over socket
+            let file = dir.path().join("file");
+            std::fs::write(&file, "data").unwrap();
+            assert!(Listener::bind(&ListenAddr::Unix(file.clone()))
+                .await
+                .is_err());
+            assert!(file.exists());
+
+            let addr = ListenAddr::Unix(dir.path().join("dc40.sock"));
+            let listener = Listener::bind(&addr).await.unwrap();
+            // a running backend keeps its socket
+            assert!(Listener::bind(&addr).await.is_err());
+
+            drop(listener);
+            Listener::bind(&addr).await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::mem;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_std::channel::{self, Receiver, Sender, TrySendError};
+use async_std::sync::Arc;
+use async_std::task;
+use shared::Event;
+
+use crate::history::RecordedEvent;
+
+/// How long events are collected before they are sent, so that bursts can be merged.
+const COALESCE_WINDOW: Duration = Duration::from_millis(100);
+
+/// How many events may wait for a client, before it is told to resync instead.
+const MAX_QUEUED_EVENTS: usize = 256;
+
+/// Creates the event queue of a connection.
+pub fn channel() -> (EventQueue, EventReceiver) {
+    let pending = Arc::new(Mutex::new(Pending::default()));
+    let (wakeup_sender, wakeup) = channel::bounded(1);
+
+    (
+        EventQueue {
+            pending: pending.clone(),
+            wakeup: wakeup_sender,
+        },
+        EventReceiver { pending, wakeup },
+    )
+}
+
+/// The sending half, fed with the events of all accounts.
+#[derive(Debug)]
+pub struct EventQueue {
+    pending: Arc<Mutex<Pending>>,
+    wakeup: Sender<()>,
+}
+
+/// The receiving half, owned by the task writing to the connection.
+///
+/// Dropping it closes the queue.
+#[derive(Debug)]
+pub struct EventReceiver {
+    pending: Arc<Mutex<Pending>>,
+    wakeup: Receiver<()>,
+}
+
+/// The [`EventReceiver`] is gone, nobody is listening anymore.
+#[derive(Debug)]
+pub struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "event queue closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+#[derive(Debug)]
+pub enum Batch {
+    Events(Vec<RecordedEvent>),
+    /// Events were dropped, the client has to reload its state.
+    Resync,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    events: VecDeque<RecordedEvent>,
+    overflowed: bool,
+}
+
+impl Pending {
+    fn push(&mut self, event: RecordedEvent) {
+        if self.overflowed {
+            return;
+        }
+
+        // keep the newer one, so that clients know they have seen everything up to it
+        self.events.retain(|queued| !replaces(&event, queued));
+
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            self.events.clear();
+            self.overflowed = true;
+            return;
+        }
+
+        self.events.push_back(event);
+    }
+}
+
+/// Whether the `newer` event makes the `queued` one redundant.
+fn replaces(newer: &RecordedEvent, queued: &RecordedEvent) -> bool {
+    if newer.account != queued.account {
+        return false;
+    }
+
+    match (&newer.event, &queued.event) {
+        // logs arrive in bursts, only the latest of each level is worth keeping
+        (Event::Log(newer), Event::Log(queued)) => {
+            mem::discriminant(newer) == mem::discriminant(queued)
+        }
+        (Event::MessagesChanged { .. }, _) | (Event::ContactsChanged { .. }, _) => {
+            newer.event == queued.event
+        }
+        _ => false,
+    }
+}
+
+impl EventQueue {
+    pub fn push(&self, event: RecordedEvent) -> Result<(), Closed> {
+        self.pending.lock().unwrap().push(event);
+
+        match self.wakeup.try_send(()) {
+            Ok(()) | Err(TrySendError::Full(())) => Ok(()),
+            Err(TrySendError::Closed(())) => Err(Closed),
+        }
+    }
+}
+
+impl EventReceiver {
+    /// Waits for events, giving them a moment to pile up and get merged.
+    ///
+    /// Returns `None` once the [`EventQueue`] is gone.
+    pub async fn next_batch(&mut self) -> Option<Batch> {
+        self.wakeup.recv().await.ok()?;
+        task::sleep(COALESCE_WINDOW).await;
+
+        let mut pending = self.pending.lock().unwrap();
+        if pending.overflowed {
+            pending.overflowed = false;
+            return Some(Batch::Resync);
+        }
+
+        Some(Batch::Events(pending.events.drain(..).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::Log;
+
+    fn changed(seq: u64, chat_id: u32) -> RecordedEvent {
+        RecordedEvent {
+            seq,
+            account: 1,
+            event: Event::MessagesChanged { chat_id },
+        }
+    }
+
+    #[test]
+    fn test_push_merges() {
+        let mut pending = Pending::default();
+        pending.push(changed(1, 10));
+        pending.push(changed(2, 11));
+        pending.push(changed(3, 10));
+
+        let seqs: Vec<_> = pending.events.iter().map(|event| event.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_push_merges_logs() {
+        let log = |seq: u64, log: Log| RecordedEvent {
+            seq,
+            account: 1,
+            event: Event::Log(log),
+        };
+
+        let mut pending = Pending::default();
+        pending.push(log(1, Log::Info("a".to_string())));
+        pending.push(log(2, Log::Warning("b".to_string())));
+        pending.push(log(3, Log::Info("c".to_string())));
+
+        let seqs: Vec<_> = pending.events.iter().map(|event| event.seq).collect();
+        assert_eq!(seqs, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_logs_do_not_resync() {
+        task::block_on(async {
+            let (queue, mut receiver) = channel();
+            for seq in 0..300 {
+                let event = RecordedEvent {
+                    seq,
+                    account: 1,
+                    event: Event::Log(Log::Info(format!("log {}", seq))),
+                };
+                queue.push(event).unwrap();
+            }
+
+            match receiver.next_batch().await {
+                Some(Batch::Events(events)) => {
+                    assert_eq!(events.len(), 1);
+                    assert_eq!(events[0].seq, 299);
+                }
+                batch => panic!("unexpected batch {:?}", batch),
+            }
+        });
+    }
+
+    #[test]
+    fn test_push_overflow() {
+        let mut pending = Pending::default();
+        for seq in 0..MAX_QUEUED_EVENTS as u64 {
+            pending.push(changed(seq, seq as u32));
+        }
+        assert_eq!(pending.events.len(), MAX_QUEUED_EVENTS);
+        assert!(!pending.overflowed);
+
+        // merged events still fit
+        pending.push(changed(1000, 0));
+        assert!(!pending.overflowed);
+
+        pending.push(changed(1001, MAX_QUEUED_EVENTS as u32));
+        assert!(pending.overflowed);
+        assert!(pending.events.is_empty());
+
+        // dropped until the client resynced
+        pending.push(changed(1002, 0));
+        assert!(pending.events.is_empty());
+    }
+
+    #[test]
+    fn test_next_batch_resync() {
+        task::block_on(async {
+            let (queue, mut receiver) = channel();
+            for seq in 0..=MAX_QUEUED_EVENTS as u64 {
+                queue.push(changed(seq, seq as u32)).unwrap();
+            }
+            assert!(matches!(receiver.next_batch().await, Some(Batch::Resync)));
+
+            queue.push(changed(1000, 0)).unwrap();
+            match receiver.next_batch().await {
+                Some(Batch::Events(events)) => assert_eq!(events.len(), 1),
+                batch => panic!("unexpected batch {:?}", batch),
+            }
+
+            drop(receiver);
+            assert!(queue.push(changed(1001, 0)).is_err());
+        });
+    }
+}
@@ -0,0 +1,643 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use async_std::sync::{Arc, RwLock};
+use async_std::task;
+use async_tungstenite::tungstenite::handshake::server;
+use async_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use async_tungstenite::tungstenite::{Error, Message};
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{stream, SinkExt, StreamExt};
+use log::{info, warn};
+use shared::*;
+
+use crate::auth::Secret;
+use crate::codec::{self, DecodeError, Encoder, InFlight, Incoming, Protocol};
+use crate::error::{error_kind, RequestError};
+use crate::listen::Listener;
+use crate::session::Session;
+use crate::state::*;
+
+/// Accepts WebSocket connections on `listener` until it fails.
+pub async fn serve(listener: Listener, local_state: LocalState, secret: Secret) {
+    match listener {
+        Listener::Tcp(listener) => {
+            while let Ok((stream, peer)) = listener.accept().await {
+                spawn_connection(
+                    stream,
+                    peer.to_string(),
+                    local_state.clone(),
+                    secret.clone(),
+                );
+            }
+        }
+        #[cfg(unix)]
+        Listener::Unix(listener) => {
+            while let Ok((stream, peer)) = listener.accept().await {
+                spawn_connection(
+                    stream,
+                    format!("{:?}", peer),
+                    local_state.clone(),
+                    secret.clone(),
+                );
+            }
+        }
+    }
+}
+
+fn spawn_connection<S>(stream: S, addr: String, local_state: LocalState, secret: Secret)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+{
+    task::spawn(async move {
+        if let Err(err) = accept_connection(stream, addr, local_state, secret).await {
+            if let Some(err) = err.downcast_ref::<Error>() {
+                match err {
+                    Error::ConnectionClosed | Error::Protocol(_) | Error::Utf8 => {}
+                    err => warn!("Error processing connection: {:?}", err),
+                }
+            } else {
+                warn!("Error processing connection: {:?}", err);
+            }
+        }
+    });
+}
+
+async fn accept_connection<S>(
+    stream: S,
+    addr: String,
+    local_state: LocalState,
+    secret: Secret,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+{
+    info!("Peer address: {}", addr);
+
+    // unauthenticated clients are rejected here, before any state is sent
+    let ws_stream = async_tungstenite::accept_hdr_async(
+        stream,
+        |request: &server::Request, response: server::Response| {
+            secret.check_request(request, response)
+        },
+    )
+    .await?;
+
+    info!("New WebSocket connection: {}", addr);
+
+    let (write, mut read) = ws_stream.split();
+
+    // the first frame decides between bincode and JSON-RPC
+    let hello = match read.next().await {
+        Some(msg) => msg?,
+        None => return Ok(()),
+    };
+    let protocol = match Protocol::detect(&hello) {
+        Some(protocol) => protocol,
+        None => {
+            warn!("unexpected first message {:?}", &hello);
+            return Ok(());
+        }
+    };
+    info!("speaking {:?}", protocol);
+
+    let mut encoder = Encoder::new(protocol);
+    let in_flight = encoder.in_flight();
+    let write = write
+        .with_flat_map(move |response| stream::iter(encoder.encode(response).into_iter().map(Ok)));
+    let write = Arc::new(RwLock::new(write));
+
+    let run_id = local_state.run_id().await;
+    let capabilities = match handshake(protocol, hello, run_id, &in_flight, write.clone()).await? {
+        Handshake::Accepted(capabilities) => capabilities,
+        Handshake::Refused(reason) => {
+            warn!("refusing connection: {}", reason);
+            write
+                .write()
+                .await
+                .get_mut()
+                .send(Message::Close(Some(CloseFrame {
+                    code: CloseCode::Policy,
+                    reason: reason.into(),
+                })))
+                .await?;
+            return Ok(());
+        }
+    };
+    info!("agreed on capabilities {:?}", capabilities);
+    // JSON-RPC calls are only complete with their result
+    let ack = protocol == Protocol::JsonRpc || capabilities.contains(capability::ACK);
+
+    let session = Session::new(local_state.default_account().await);
+
+    info!("send update");
+    local_state.send_update(&session, write.clone()).await?;
+
+    info!("subscribe_all");
+    local_state
+        .subscribe_all(session.clone(), write.clone())
+        .await?;
+
+    info!("start loop");
+
+    while let Some(msg) = read.next().await {
+        let msg = msg?;
+        if msg.is_close() {
+            info!("closing connection");
+            return Ok(());
+        }
+
+        if !msg.is_binary() && !msg.is_text() {
+            warn!("ignoring unknown message {:?}", &msg);
+            continue;
+        }
+        let parsed = codec::decode(protocol, msg, &in_flight);
+        info!("request: {:?}", &parsed);
+        let response = match parsed {
+            Ok(Incoming {
+                request_id,
+                request,
+            }) => {
+                if let Some(request_id) = request_id {
+                    in_flight.start(request_id);
+                }
+                // notifications are never answered
+                match process_request(request, write.clone(), &local_state, &session).await {
+                    Ok(()) if ack => request_id.map(|request_id| Response::Ack { request_id }),
+                    Ok(()) => None,
+                    Err(err) => {
+                        warn!("error processing request {:?}: {:?}", request_id, err);
+                        request_id.map(|request_id| Response::Error {
+                            request_id: Some(request_id),
+                            kind: error_kind(&err),
+                            message: format!("{:#}", err),
+                        })
+                    }
+                }
+            }
+            Err(DecodeError {
+                is_notification: true,
+                error,
+                ..
+            }) => {
+                warn!("invalid notification {:#}", error);
+                None
+            }
+            Err(DecodeError {
+                request_id,
+                kind,
+                error,
+                ..
+            }) => {
+                warn!("invalid msg {:#}", error);
+                Some(Response::Error {
+                    request_id,
+                    kind,
+                    message: format!("{:#}", error),
+                })
+            }
+        };
+        if let Some(response) = response {
+            send(write.clone(), response).await?;
+        }
+    }
+
+    Ok(())
+}
+
+enum Handshake {
+    Accepted(HashSet<String>),
+    /// The client was told why, the connection still needs to be closed.
+    Refused(String),
+}
+
+/// Answers the client's [`Request::Hello`], which has to be its first message.
+async fn handshake<T>(
+    protocol: Protocol,
+    msg: Message,
+    run_id: u64,
+    in_flight: &InFlight,
+    writer: Arc<RwLock<T>>,
+) -> Result<Handshake>
+where
+    T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
+    T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
+{
+    let (request_id, protocol_version, capabilities) = match codec::decode(protocol, msg, in_flight)
+    {
+        Ok(Incoming {
+            request_id,
+            request:
+                Request::Hello {
+                    protocol_version,
+                    capabilities,
+                },
+        }) => (request_id, protocol_version, capabilities),
+        Ok(Incoming { request_id, .. }) | Err(DecodeError { request_id, .. }) => {
+            return refuse(
+                protocol,
+                writer,
+                request_id,
+                run_id,
+                ErrorKind::InvalidRequest,
+                "expected Hello",
+            )
+            .await;
+        }
+    };
+
+    if protocol_version != PROTOCOL_VERSION {
+        let message = format!(
+            "incompatible protocol version {}, the backend speaks version {}",
+            protocol_version, PROTOCOL_VERSION
+        );
+        return refuse(
+            protocol,
+            writer,
+            request_id,
+            run_id,
+            ErrorKind::IncompatibleProtocol,
+            message,
+        )
+        .await;
+    }
+
+    let capabilities: HashSet<String> = capabilities
+        .into_iter()
+        .filter(|capability| CAPABILITIES.contains(&capability.as_str()))
+        .collect();
+
+    // a Hello sent as notification gets its Welcome pushed
+    if let Some(request_id) = request_id {
+        in_flight.start(request_id);
+    }
+    send(
+        writer.clone(),
+        Response::Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: capabilities.iter().cloned().collect(),
+            run_id,
+            refusal: None,
+        },
+    )
+    .await?;
+    if let (Protocol::JsonRpc, Some(request_id)) = (protocol, request_id) {
+        send(writer, Response::Ack { request_id }).await?;
+    }
+
+    Ok(Handshake::Accepted(capabilities))
+}
+
+/// Reports why the connection is refused.
+///
+/// Bincode clients get a [`Response::Welcome`], the only response that decodes the same
+/// across protocol versions.
+async fn refuse<T>(
+    protocol: Protocol,
+    writer: Arc<RwLock<T>>,
+    request_id: Option<u32>,
+    run_id: u64,
+    kind: ErrorKind,
+    message: impl Into<String>,
+) -> Result<Handshake>
+where
+    T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
+    T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
+{
+    let message = message.into();
+    let response = match protocol {
+        Protocol::Bincode => Response::Welcome {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Vec::new(),
+            run_id,
+            refusal: Some(message.clone()),
+        },
+        Protocol::JsonRpc => Response::Error {
+            request_id,
+            kind,
+            message: message.clone(),
+        },
+    };
+    send(writer, response).await?;
+
+    Ok(Handshake::Refused(message))
+}
+
+async fn process_request<T>(
+    request: Request,
+    writer: Arc<RwLock<T>>,
+    local_state: &LocalState,
+    session: &Session,
+) -> Result<()>
+where
+    T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
+    T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
+{
+    match request {
+        Request::Hello { .. } => {
+            return Err(
+                RequestError::new(ErrorKind::InvalidRequest, "handshake already done").into(),
+            );
+        }
+        Request::Login { email, password } => {
+            let email = email.to_lowercase();
+            let (id, ctx) = local_state.add_account().await?;
+
+            local_state.login(id, &ctx, &email, &password).await?;
+
+            // a failed login was recorded as error and removed the account again
+            if local_state.select_account(session, id).await.is_ok() {
+                local_state
+                    .send_account_details(session, id, writer.clone())
+                    .await?;
+            } else {
+                local_state.send_update(session, writer.clone()).await?;
+            }
+        }
+
+        Request::SelectChat {
+            account: id,
+            chat_id,
+        } => {
+            let resp = local_state.select_chat(session, id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::PinChat {
+            account: id,
+            chat_id,
+        } => {
+            let resp = local_state.pin_chat(session, id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::UnpinChat {
+            account: id,
+            chat_id,
+        } => {
+            let resp = local_state.unpin_chat(session, id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::ArchiveChat {
+            account: id,
+            chat_id,
+        } => {
+            let resp = local_state.archive_chat(session, id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::UnarchiveChat {
+            account: id,
+            chat_id,
+        } => {
+            let resp = local_state.unarchive_chat(session, id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::LoadChatList {
+            start_index,
+            stop_index,
+            query,
+            flags,
+        } => {
+            let resp = local_state
+                .load_chat_list(session, start_index, stop_index, query, flags)
+                .await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::LoadMessageList {
+            start_index,
+            stop_index,
+        } => {
+            let resp = local_state
+                .load_message_list(session, Some((start_index, stop_index)))
+                .await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::SelectAccount { account } => {
+            info!("selecting account {}", account);
+            let resp = local_state.select_account(session, account).await?;
+            let has_selected_chat = matches!(
+                resp,
+                Response::Account {
+                    chat_id: Some(_),
+                    ..
+                }
+            );
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+
+            if has_selected_chat {
+                let resp = local_state.load_message_list(session, None).await?;
+                send(writer.clone(), resp).await?;
+            }
+        }
+        Request::SendTextMessage { text } => {
+            local_state.send_text_message(session, text).await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::SendFileMessage {
+            typ,
+            path,
+            text,
+            mime,
+        } => {
+            local_state
+                .send_file_message(session, typ, path, text, mime)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::MaybeNetwork => {
+            info!("maybe network");
+            local_state.maybe_network().await?;
+        }
+        Request::AcceptContactRequest {
+            account: id,
+            chat_id,
+        } => {
+            local_state.accept_contact_request(id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::BlockContact {
+            account: id,
+            chat_id,
+        } => {
+            local_state.block_contact(id, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::GetAccountDetail { id } => {
+            local_state
+                .send_account_details(session, id, writer)
+                .await?;
+        }
+        Request::GetContacts => local_state.send_contacts(session, writer).await?,
+        Request::CreateChat(contacts) => {
+            let resp = local_state.create_chat(session, contacts).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::CreateGroupChat(contacts, chat_name) => {
+            // members that could not be added are reported as error of the account
+            let resp = local_state
+                .create_group_chat(session, contacts, &chat_name)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::DismissError { id } => {
+            local_state.dismiss_error(id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::Resume { run_id, last_seq } => {
+            local_state
+                .resume(session, run_id, last_seq, writer)
+                .await?;
+        }
+        Request::SendReply {
+            quote_msg_id,
+            text,
+            file,
+        } => {
+            local_state
+                .send_reply(session, quote_msg_id, text, file)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::DeleteMessages { account, msg_ids } => {
+            local_state.delete_messages(account, msg_ids).await?;
+        }
+        Request::ForwardMessages {
+            account,
+            msg_ids,
+            target_chat_id,
+        } => {
+            local_state
+                .forward_messages(account, msg_ids, target_chat_id)
+                .await?;
+        }
+        Request::SetDraft {
+            account,
+            chat_id,
+            text,
+            quote_msg_id,
+            file,
+        } => {
+            local_state
+                .set_draft(account, chat_id, text, quote_msg_id, file)
+                .await?;
+        }
+        Request::SearchMessages {
+            account,
+            chat_id,
+            query,
+        } => {
+            let resp = local_state.search_messages(account, chat_id, query).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::GetMessageInfo { account, msg_id } => {
+            let resp = local_state.get_message_info(account, msg_id).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::MuteChat {
+            account,
+            chat_id,
+            duration,
+        } => {
+            local_state.mute_chat(account, chat_id, duration).await?;
+        }
+        Request::SetEphemeralTimer {
+            account,
+            chat_id,
+            timer,
+        } => {
+            local_state
+                .set_ephemeral_timer(session, account, chat_id, timer)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::GetContactDetail {
+            account,
+            contact_id,
+        } => {
+            let resp = local_state.get_contact_detail(account, contact_id).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::RenameContact {
+            account,
+            contact_id,
+            name,
+        } => {
+            let resp = local_state
+                .rename_contact(account, contact_id, &name)
+                .await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::AddChatMember {
+            account,
+            chat_id,
+            contact_id,
+        } => {
+            local_state
+                .add_chat_member(session, account, chat_id, contact_id)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::RemoveChatMember {
+            account,
+            chat_id,
+            contact_id,
+        } => {
+            local_state
+                .remove_chat_member(session, account, chat_id, contact_id)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::RenameChat {
+            account,
+            chat_id,
+            name,
+        } => {
+            local_state
+                .rename_chat(session, account, chat_id, &name)
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::SetChatProfileImage {
+            account,
+            chat_id,
+            image,
+        } => {
+            local_state
+                .set_chat_profile_image(session, account, chat_id, image.as_deref())
+                .await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::LeaveChat { account, chat_id } => {
+            local_state.leave_chat(session, account, chat_id).await?;
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::GetChatMembers { account, chat_id } => {
+            let resp = local_state.get_chat_members(account, chat_id).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::GetBlockedContacts { account } => {
+            let resp = local_state.get_blocked_contacts(account).await?;
+            send(writer.clone(), resp).await?;
+        }
+        Request::UnblockContact {
+            account,
+            contact_id,
+        } => {
+            local_state.unblock_contact(account, contact_id).await?;
+            // the chat with the contact shows up again
+            local_state.send_update(session, writer.clone()).await?;
+        }
+        Request::CreateContact { name, email } => {
+            let resp = local_state.create_contact(session, &name, &email).await?;
+            local_state.send_update(session, writer.clone()).await?;
+            send(writer.clone(), resp).await?;
+        }
+    }
+    Ok(())
+}
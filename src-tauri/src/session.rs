@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use async_std::sync::{Arc, RwLock};
+use deltachat::chat::ChatId;
+use shared::{ChatListFlags, ChatState};
+
+/// What a single connection is looking at.
+///
+/// Every WebSocket connection gets its own session, so that windows and clients don't
+/// change each other's selected account or chat.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub state: Arc<RwLock<SessionState>>,
+}
+
+sa::assert_impl_all!(Session: Send);
+
+#[derive(Debug, Default)]
+pub struct SessionState {
+    pub selected_account: Option<u32>,
+    /// The selected chat of each account, kept while switching between accounts.
+    pub selected_chats: HashMap<u32, SelectedChat>,
+    /// The range of the chat list the client loaded last.
+    pub chat_list_range: Option<(usize, usize)>,
+    /// The filters of the chat list the client loaded last.
+    pub chat_list_query: Option<String>,
+    pub chat_list_flags: ChatListFlags,
+    /// Events after this sequence number are sent live, earlier ones can be resumed.
+    pub live_since: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelectedChat {
+    pub id: ChatId,
+    pub chat: Option<ChatState>,
+    /// The range of the message list the client loaded last.
+    pub range: Option<(usize, usize)>,
+}
+
+impl Session {
+    pub fn new(selected_account: Option<u32>) -> Self {
+        Session {
+            state: Arc::new(RwLock::new(SessionState {
+                selected_account,
+                ..Default::default()
+            })),
+        }
+    }
+}
+
+impl SessionState {
+    pub fn selected_chat(&self, account: u32) -> Option<&SelectedChat> {
+        self.selected_chats.get(&account)
+    }
+
+    pub fn select_chat(&mut self, account: u32, id: ChatId, chat: Option<ChatState>) {
+        self.selected_chats.insert(
+            account,
+            SelectedChat {
+                id,
+                chat,
+                range: None,
+            },
+        );
+    }
+
+    /// Replaces the state of the chat, if it is still selected.
+    pub fn refresh_chat(&mut self, account: u32, id: ChatId, chat: Option<ChatState>) {
+        if let Some(selected) = self.selected_chats.get_mut(&account) {
+            if selected.id == id {
+                selected.chat = chat;
+            }
+        }
+    }
+
+    /// Remembers the loaded range of the message list, if the chat is still selected.
+    pub fn set_message_list_range(&mut self, account: u32, id: ChatId, range: (usize, usize)) {
+        if let Some(selected) = self.selected_chats.get_mut(&account) {
+            if selected.id == id {
+                selected.range = Some(range);
+            }
+        }
+    }
+}
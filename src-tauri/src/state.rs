@@ -1,36 +1,47 @@
 use std::collections::{HashMap, HashSet};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Context as _, Result};
 use async_std::sync::{Arc, RwLock};
 use async_std::task;
 use async_std::{path::Path, prelude::*};
-use async_tungstenite::tungstenite::{Error, Message};
+use async_tungstenite::tungstenite::Error;
 use broadcaster::BroadcastChannel;
+use chrono::Utc;
 use deltachat::chat::{self, Chat, ChatId, ProtectionStatus};
-use deltachat::contact::Contact;
+use deltachat::contact;
 use deltachat::context::Context;
-use deltachat::{message, EventType};
+use deltachat::message::{self, MsgId};
+use deltachat::EventType;
 use futures::future::join_all;
 use futures::sink::SinkExt;
-use futures::stream::{self, StreamExt};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use itertools::Itertools;
 use log::*;
 use num_traits::FromPrimitive;
 use shared::*;
 
 use crate::account::*;
+use crate::error::*;
+use crate::history::{History, RecordedEvent};
+use crate::queue::{Batch, Closed};
+use crate::session::*;
 
 #[derive(Debug, Clone)]
 pub struct LocalState {
     inner: Arc<RwLock<LocalStateInner>>,
-    events: BroadcastChannel<deltachat::Event>,
+    events: BroadcastChannel<RecordedEvent>,
+    history: Arc<RwLock<History>>,
 }
 
+/// How many errors are kept around before the oldest ones are dropped.
+const MAX_ERRORS: usize = 50;
+
 #[derive(Debug)]
 struct LocalStateInner {
     account_states: HashMap<u32, Account>,
     accounts: deltachat::accounts::Accounts,
-    errors: Vec<anyhow::Error>,
+    errors: Vec<ErrorInfo>,
+    next_error_id: u32,
 }
 
 sa::assert_impl_all!(LocalState: Send);
@@ -38,23 +49,22 @@ sa::assert_impl_all!(LocalState: Send);
 impl LocalState {
     pub async fn new() -> Result<Self> {
         let inner = LocalStateInner::new().await?;
-
-        let receiver = BroadcastChannel::new();
-        let sender = receiver.clone();
         let mut events = inner.accounts.get_event_emitter().await;
 
+        let local_state = Self {
+            inner: Arc::new(RwLock::new(inner)),
+            events: BroadcastChannel::new(),
+            history: Default::default(),
+        };
+
+        let ls = local_state.clone();
         task::spawn(async move {
             while let Ok(Some(event)) = events.recv().await {
-                if let Err(err) = sender.send(&event).await {
-                    error!("Failed to send event: {:?}", err);
-                }
+                ls.handle_event(event).await;
             }
         });
 
-        Ok(Self {
-            inner: Arc::new(RwLock::new(inner)),
-            events: receiver,
-        })
+        Ok(local_state)
     }
 
     async fn with_account_state<F>(&self, id: u32, f: F)
@@ -62,172 +72,188 @@ impl LocalState {
         F: FnOnce(&mut crate::account::AccountState),
     {
         let ls = self.inner.read().await;
-        let account = ls.account_states.get(&id).expect("missing account");
+        // events can still arrive for accounts that were just removed
+        if let Some(account) = ls.account_states.get(&id) {
+            let state = &mut account.state.write().await;
+            f(state);
+        }
+    }
+
+    /// Numbers the events clients care about and hands them to all connections.
+    async fn handle_event(&self, event: deltachat::Event) {
+        let account = event.id;
+        match self.translate_event(event).await {
+            Ok(Some(event)) => {
+                // held while sending, so subscribing is atomic with respect to the history
+                let mut history = self.history.write().await;
+                let event = history.record(account, event);
+                if let Err(err) = self.events.send(&event).await {
+                    error!("Failed to send event: {:?}", err);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => warn!("failed to handle event: {:?}", err),
+        }
+    }
+
+    async fn translate_event(&self, event: deltachat::Event) -> Result<Option<shared::Event>> {
+        let ctx = self
+            .inner
+            .read()
+            .await
+            .accounts
+            .get_account(event.id)
+            .await
+            .ok_or_else(|| invalid_account(event.id))?;
+
+        let event = match event.typ {
+            EventType::ConfigureProgress { progress, .. } => {
+                if progress == 0 {
+                    self.with_account_state(event.id, |state| {
+                        state.logged_in = Login::Error("failed to login".into());
+                    })
+                    .await;
+                    shared::Event::Configure(shared::Progress::Error)
+                } else {
+                    let p = if progress == 1000 {
+                        shared::Progress::Success
+                    } else {
+                        self.with_account_state(event.id, |state| {
+                            state.logged_in = Login::Progress(progress);
+                        })
+                        .await;
+                        shared::Progress::Step(progress)
+                    };
+                    shared::Event::Configure(p)
+                }
+            }
+            EventType::ImexProgress(progress) => {
+                if progress == 0 {
+                    self.with_account_state(event.id, |state| {
+                        state.logged_in = Login::Error("failed to import".into());
+                    })
+                    .await;
+                    shared::Event::Imex(shared::Progress::Error)
+                } else {
+                    let p = if progress == 1000 {
+                        shared::Progress::Success
+                    } else {
+                        self.with_account_state(event.id, |state| {
+                            state.logged_in = Login::Progress(progress);
+                        })
+                        .await;
+                        shared::Progress::Step(progress)
+                    };
+                    shared::Event::Imex(p)
+                }
+            }
+            EventType::ImapConnected(_) | EventType::SmtpConnected(_) => {
+                info!("logged in");
+                self.with_account_state(event.id, |state| {
+                    state.logged_in = Login::Success;
+                })
+                .await;
+                shared::Event::Connected
+            }
+            EventType::IncomingMsg { chat_id, msg_id } => {
+                let msg = message::Message::load_from_db(&ctx, msg_id)
+                    .await
+                    .map_err(|err| anyhow!("failed to load msg: {}: {}", msg_id, err))?;
+                let chat = Chat::load_from_db(&ctx, chat_id)
+                    .await
+                    .map_err(|err| anyhow!("failed to load chat: {:?}", err))?;
+
+                if chat.is_muted() {
+                    // still show the message, just don't notify
+                    return Ok(Some(shared::Event::MessagesChanged {
+                        chat_id: chat_id.to_u32(),
+                    }));
+                }
 
-        let state = &mut account.state.write().await;
-        f(state);
+                shared::Event::MessageIncoming {
+                    chat_id: chat_id.to_u32(),
+                    title: chat.get_name().to_string(),
+                    body: msg.get_text().unwrap_or_default(),
+                }
+            }
+            EventType::MsgDelivered { chat_id, .. }
+            | EventType::MsgFailed { chat_id, .. }
+            | EventType::MsgsChanged { chat_id, .. }
+            | EventType::MsgRead { chat_id, .. }
+            | EventType::ChatModified(chat_id)
+            | EventType::ChatEphemeralTimerModified { chat_id, .. }
+            | EventType::MsgsNoticed(chat_id) => shared::Event::MessagesChanged {
+                chat_id: chat_id.to_u32(),
+            },
+            EventType::ContactsChanged(contact_id) => shared::Event::ContactsChanged { contact_id },
+            EventType::Info(msg) => {
+                info!("{}", msg);
+                shared::Event::Log(shared::Log::Info(msg))
+            }
+            EventType::Warning(msg) => {
+                warn!("{}", msg);
+                shared::Event::Log(shared::Log::Warning(msg))
+            }
+            EventType::Error(msg) => {
+                error!("{}", msg);
+                shared::Event::Log(shared::Log::Error(msg))
+            }
+            _ => {
+                debug!("{:?}", event);
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(event))
     }
 
-    pub async fn subscribe_all<T>(&self, writer: Arc<RwLock<T>>) -> Result<()>
+    pub async fn subscribe_all<T>(&self, session: Session, writer: Arc<RwLock<T>>) -> Result<()>
     where
-        T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static,
+        T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
-        let mut events = self.events.clone();
-        let ls = self.clone();
+        let (queue, mut receiver) = crate::queue::channel();
 
+        // writes the queued events, so that a slow client does not hold up the core events
+        let ls = self.clone();
         task::spawn(async move {
-            while let Some(event) = events.next().await {
-                let ctx = ls
-                    .inner
-                    .read()
-                    .await
-                    .accounts
-                    .get_account(event.id)
-                    .await
-                    .unwrap();
-
-                let res = match event.typ {
-                    EventType::ConfigureProgress { progress, .. } => {
-                        if progress == 0 {
-                            ls.with_account_state(event.id, |state| {
-                                state.logged_in = Login::Error("failed to login".into());
+            while let Some(batch) = receiver.next_batch().await {
+                let res = match batch {
+                    Batch::Events(events) => {
+                        stream::iter(events)
+                            .map(Ok)
+                            .try_for_each(|event| {
+                                ls.send_session_event(&session, writer.clone(), event)
                             })
-                            .await;
-                            ls.send_event(
-                                writer.clone(),
-                                event.id,
-                                shared::Event::Configure(shared::Progress::Error),
-                            )
-                            .await
-                        } else {
-                            let p = if progress == 1000 {
-                                shared::Progress::Success
-                            } else {
-                                ls.with_account_state(event.id, |state| {
-                                    state.logged_in = Login::Progress(progress);
-                                })
-                                .await;
-                                shared::Progress::Step(progress)
-                            };
-                            ls.send_event(writer.clone(), event.id, shared::Event::Configure(p))
-                                .await
-                        }
-                    }
-                    EventType::ImexProgress(progress) => {
-                        if progress == 0 {
-                            ls.with_account_state(event.id, |state| {
-                                state.logged_in = Login::Error("failed to import".into());
-                            })
-                            .await;
-                            ls.send_event(
-                                writer.clone(),
-                                event.id,
-                                shared::Event::Imex(shared::Progress::Error),
-                            )
-                            .await
-                        } else {
-                            let p = if progress == 1000 {
-                                shared::Progress::Success
-                            } else {
-                                ls.with_account_state(event.id, |state| {
-                                    state.logged_in = Login::Progress(progress);
-                                })
-                                .await;
-                                shared::Progress::Step(progress)
-                            };
-                            ls.send_event(writer.clone(), event.id, shared::Event::Imex(p))
-                                .await
-                        }
-                    }
-                    EventType::ImapConnected(_) | EventType::SmtpConnected(_) => {
-                        info!("logged in");
-                        ls.with_account_state(event.id, |state| {
-                            state.logged_in = Login::Success;
-                        })
-                        .await;
-                        ls.send_event(writer.clone(), event.id, shared::Event::Connected)
-                            .await
-                    }
-                    EventType::IncomingMsg { chat_id, msg_id } => {
-                        let load = || async {
-                            let msg = message::Message::load_from_db(&ctx, msg_id).await.map_err(
-                                |err| anyhow!("failed to load msg: {}: {}", msg_id, err),
-                            )?;
-                            let chat = Chat::load_from_db(&ctx, chat_id)
-                                .await
-                                .map_err(|err| anyhow!("failed to load chat: {:?}", err))?;
-
-                            ls.send_event(
-                                writer.clone(),
-                                event.id,
-                                shared::Event::MessageIncoming {
-                                    chat_id: chat_id.to_u32(),
-                                    title: chat.get_name().to_string(),
-                                    body: msg.get_text().unwrap_or_default(),
-                                },
-                            )
                             .await
-                        };
-                        load().await
                     }
-                    EventType::MsgDelivered { chat_id, .. }
-                    | EventType::MsgFailed { chat_id, .. }
-                    | EventType::MsgsChanged { chat_id, .. }
-                    | EventType::MsgRead { chat_id, .. }
-                    | EventType::ChatModified(chat_id)
-                    | EventType::MsgsNoticed(chat_id) => {
-                        ls.send_event(
-                            writer.clone(),
-                            event.id,
-                            shared::Event::MessagesChanged {
-                                chat_id: chat_id.to_u32(),
-                            },
-                        )
-                        .await
-                    }
-                    EventType::Info(msg) => {
-                        info!("{}", msg);
-                        ls.send_event(
-                            writer.clone(),
-                            event.id,
-                            shared::Event::Log(shared::Log::Info(msg)),
-                        )
-                        .await
-                    }
-                    EventType::Warning(msg) => {
-                        warn!("{}", msg);
-                        ls.send_event(
-                            writer.clone(),
-                            event.id,
-                            shared::Event::Log(shared::Log::Warning(msg)),
-                        )
-                        .await
-                    }
-                    EventType::Error(msg) => {
-                        error!("{}", msg);
-                        ls.send_event(
-                            writer.clone(),
-                            event.id,
-                            shared::Event::Log(shared::Log::Error(msg)),
-                        )
-                        .await
-                    }
-                    _ => {
-                        debug!("{:?}", event);
-                        Ok(())
+                    Batch::Resync => {
+                        info!("client fell behind, resyncing");
+                        ls.resync(&session, writer.clone()).await
                     }
                 };
 
-                match res {
-                    Ok(_) => {}
-                    Err(err) => match err.downcast_ref::<Error>() {
-                        Some(Error::ConnectionClosed) => {
-                            // stop listening
-                            break;
-                        }
-                        _ => {}
-                    },
+                if let Err(err) = res {
+                    match err.downcast_ref::<Error>() {
+                        Some(Error::ConnectionClosed) => {}
+                        _ => warn!("failed to send events: {:?}", err),
+                    }
+                    // stop listening, dropping the receiver closes the queue
+                    break;
+                }
+            }
+        });
+
+        let mut events = {
+            let history = self.history.read().await;
+            session.state.write().await.live_since = history.last_seq();
+            self.events.clone()
+        };
+        task::spawn(async move {
+            while let Some(event) = events.next().await {
+                if let Err(Closed) = queue.push(event) {
+                    // stop listening
+                    break;
                 }
             }
         });
@@ -235,6 +261,50 @@ impl LocalState {
         Ok(())
     }
 
+    /// Identifies this run of the backend, see [`Request::Resume`].
+    pub async fn run_id(&self) -> u64 {
+        self.history.read().await.run_id()
+    }
+
+    /// Replays the events after `last_seq`, or resyncs the client if they are gone.
+    pub async fn resume<T>(
+        &self,
+        session: &Session,
+        run_id: u64,
+        last_seq: u64,
+        writer: Arc<RwLock<T>>,
+    ) -> Result<()>
+    where
+        T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
+        T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
+    {
+        let missed = self.history.read().await.since(run_id, last_seq);
+        let live_since = session.state.read().await.live_since;
+        match missed {
+            Some(events) => {
+                info!("replaying events {}..={}", last_seq + 1, live_since);
+                // the later ones are sent live
+                for event in events.into_iter().filter(|event| event.seq <= live_since) {
+                    self.send_event(writer.clone(), event).await?;
+                }
+                Ok(())
+            }
+            None => {
+                info!("events after {} are gone, resyncing", last_seq);
+                self.resync(session, writer).await
+            }
+        }
+    }
+
+    async fn resync<T>(&self, session: &Session, writer: Arc<RwLock<T>>) -> Result<()>
+    where
+        T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
+        T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
+    {
+        send(writer.clone(), Response::Resync).await?;
+        self.send_update(session, writer).await
+    }
+
     pub async fn add_account(&self) -> Result<(u32, Context)> {
         let mut ls = self.inner.write().await;
         let id = ls.accounts.add_account().await?;
@@ -258,7 +328,13 @@ impl LocalState {
             .await;
         if let Err(err) = res {
             let mut ls = self.inner.write().await;
-            ls.errors.push(err);
+            ls.push_error(
+                ErrorKind::Login,
+                Some(id),
+                Some(email.to_string()),
+                true,
+                &err,
+            );
             ls.account_states.remove(&id);
             ls.accounts.remove_account(id).await?;
         }
@@ -266,36 +342,70 @@ impl LocalState {
         Ok(())
     }
 
-    pub async fn send_account_details<T>(&self, id: u32, writer: Arc<RwLock<T>>) -> Result<()>
+    /// The directories holding the files of the accounts, e.g. attachments and avatars.
+    pub async fn blob_dirs(&self) -> Vec<async_std::path::PathBuf> {
+        let ls = self.inner.read().await;
+        let mut dirs = Vec::new();
+        for id in ls.accounts.get_all().await {
+            if let Some(ctx) = ls.accounts.get_account(id).await {
+                dirs.push(ctx.get_blobdir().to_path_buf());
+            }
+        }
+
+        dirs
+    }
+
+    /// The account new sessions start out with, the one selected last.
+    pub async fn default_account(&self) -> Option<u32> {
+        let ls = self.inner.read().await;
+        ls.accounts
+            .get_selected_account()
+            .await
+            .map(|ctx| ctx.get_id())
+    }
+
+    pub async fn send_account_details<T>(
+        &self,
+        session: &Session,
+        id: u32,
+        writer: Arc<RwLock<T>>,
+    ) -> Result<()>
     where
-        T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static,
+        T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
         let ls = self.inner.write().await;
         let ctx = ls.accounts.get_account(id).await.unwrap();
 
-        ls.send_update(writer.clone()).await?;
+        ls.send_update(session, writer.clone()).await?;
 
         if let Some(account) = ls.account_states.get(&id) {
             // chat list
-            let (range, len, chats) = account.load_chat_list(&ctx, 0, 10).await?;
-            send(writer.clone(), Response::ChatList { range, len, chats }).await?;
-
-            // send selected chat if exists
-            if let Some(_selected_chat) = account.state.read().await.selected_chat_id {
-                let (chat_id, range, items, messages) =
-                    account.load_message_list(&ctx, None).await?;
-
-                send(
-                    writer,
-                    Response::MessageList {
-                        chat_id,
-                        range,
-                        items,
-                        messages,
-                    },
+            let ((start_index, stop_index), query, flags) = {
+                let session = session.state.read().await;
+                (
+                    session.chat_list_range.unwrap_or((0, 10)),
+                    session.chat_list_query.clone(),
+                    session.chat_list_flags,
                 )
+            };
+            let (range, len, archived_len, chats) = account
+                .load_chat_list(&ctx, start_index, stop_index, query.as_deref(), flags)
                 .await?;
+            let resp = Response::ChatList {
+                range,
+                len,
+                archived_len,
+                chats,
+            };
+            send(writer.clone(), resp).await?;
+
+            // send selected chat if exists
+            let selected = session.state.read().await.selected_chat(id).cloned();
+            if let Some(selected) = selected {
+                let resp =
+                    load_message_list(session, account, &ctx, selected.id, selected.range).await?;
+                send(writer, resp).await?;
             }
         }
 
@@ -313,8 +423,11 @@ impl LocalState {
             .import(&ctx, path)
             .await;
         if let Err(err) = res {
+            use deltachat::config::Config;
+            // the backup may have been imported partially
+            let addr = ctx.get_config(Config::Addr).await.ok().flatten();
             let mut ls = self.inner.write().await;
-            ls.errors.push(err);
+            ls.push_error(ErrorKind::Import, Some(id), addr, true, &err);
             ls.account_states.remove(&id);
             ls.accounts.remove_account(id).await?;
         }
@@ -322,129 +435,453 @@ impl LocalState {
         Ok(())
     }
 
-    pub async fn send_update<T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static>(
+    pub async fn send_update<T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static>(
         &self,
+        session: &Session,
         writer: Arc<RwLock<T>>,
     ) -> Result<()>
     where
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
-        self.inner.read().await.send_update(writer).await
+        self.inner.read().await.send_update(session, writer).await
     }
 
-    pub async fn send_event<T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static>(
+    pub async fn send_event<T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static>(
         &self,
         writer: Arc<RwLock<T>>,
-        account: u32,
-        event: shared::Event,
+        event: RecordedEvent,
     ) -> Result<()>
     where
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
-        self.inner
-            .read()
-            .await
-            .send_event(writer, account, event)
-            .await
+        self.inner.read().await.send_event(writer, event).await
+    }
+
+    /// Sends the event, followed by an update if it changed the selected chat, whose
+    /// header shows e.g. the name, image and ephemeral timer set by other members.
+    async fn send_session_event<T>(
+        &self,
+        session: &Session,
+        writer: Arc<RwLock<T>>,
+        event: RecordedEvent,
+    ) -> Result<()>
+    where
+        T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
+        T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
+    {
+        let changed = match event.event {
+            shared::Event::MessagesChanged { chat_id } => {
+                Some((event.account, ChatId::new(chat_id)))
+            }
+            _ => None,
+        };
+        self.send_event(writer.clone(), event).await?;
+
+        if let Some((account_id, chat_id)) = changed {
+            let is_selected = {
+                let session = session.state.read().await;
+                session.selected_account == Some(account_id)
+                    && session
+                        .selected_chat(account_id)
+                        .map(|selected| selected.id)
+                        == Some(chat_id)
+            };
+            if is_selected {
+                let ls = self.inner.read().await;
+                if let Some(account) = ls.account_states.get(&account_id) {
+                    let ctx = ls.accounts.get_account(account_id).await.unwrap();
+                    refresh_chat(session, account, &ctx, chat_id).await?;
+                    ls.send_update(session, writer).await?;
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    pub async fn select_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn select_chat(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Response> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).await.unwrap();
             let chat = ChatId::new(chat_id);
-            account.select_chat(&ctx, chat).await?;
-
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
+            let selected_chat = account.select_chat(&ctx, chat).await?;
+            session
+                .state
+                .write()
+                .await
+                .select_chat(account_id, chat, selected_chat);
 
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            load_message_list(session, account, &ctx, chat, None).await
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
         }
     }
 
-    pub async fn pin_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn pin_chat(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Response> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).await.unwrap();
             let chat = ChatId::new(chat_id);
             account.pin_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
-
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            load_selected_message_list(session, account, &ctx, None).await
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
         }
     }
 
-    pub async fn unpin_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn unpin_chat(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Response> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).await.unwrap();
             let chat = ChatId::new(chat_id);
             account.unpin_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
-
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            load_selected_message_list(session, account, &ctx, None).await
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
         }
     }
 
-    pub async fn archive_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn archive_chat(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Response> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).await.unwrap();
             let chat = ChatId::new(chat_id);
             account.archive_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
-
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            load_selected_message_list(session, account, &ctx, None).await
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
         }
     }
 
-    pub async fn unarchive_chat(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+    pub async fn unarchive_chat(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+    ) -> Result<Response> {
         let ls = self.inner.write().await;
         if let Some(account) = ls.account_states.get(&account_id) {
             let ctx = ls.accounts.get_account(account_id).await.unwrap();
             let chat = ChatId::new(chat_id);
-            account.unpin_chat(&ctx, chat).await?;
+            account.unarchive_chat(&ctx, chat).await?;
 
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, None).await?;
+            load_selected_message_list(session, account, &ctx, None).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
 
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
+    pub async fn delete_messages(&self, account_id: u32, msg_ids: Vec<u32>) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let msg_ids = msg_ids.into_iter().map(MsgId::new).collect();
+            account.delete_messages(&ctx, msg_ids).await?;
+
+            Ok(())
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn forward_messages(
+        &self,
+        account_id: u32,
+        msg_ids: Vec<u32>,
+        target_chat_id: u32,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let msg_ids = msg_ids.into_iter().map(MsgId::new).collect();
+            let chat = ChatId::new(target_chat_id);
+            account.forward_messages(&ctx, msg_ids, chat).await?;
+
+            Ok(())
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn mute_chat(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        duration: MuteDuration,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.mute_chat(&ctx, chat, duration).await?;
+
+            Ok(())
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn set_ephemeral_timer(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+        timer: u32,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.set_ephemeral_timer(&ctx, chat, timer).await?;
+
+            // the chat header shows the timer
+            refresh_chat(session, account, &ctx, chat).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn add_chat_member(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+        contact_id: u32,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.add_chat_member(&ctx, chat, contact_id).await?;
+
+            refresh_chat(session, account, &ctx, chat).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn remove_chat_member(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+        contact_id: u32,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.remove_chat_member(&ctx, chat, contact_id).await?;
+
+            refresh_chat(session, account, &ctx, chat).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn rename_chat(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+        name: &str,
+    ) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(
+                RequestError::new(ErrorKind::InvalidRequest, "the name must not be empty").into(),
+            );
+        }
+
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.rename_chat(&ctx, chat, name).await?;
+
+            refresh_chat(session, account, &ctx, chat).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn set_chat_profile_image(
+        &self,
+        session: &Session,
+        account_id: u32,
+        chat_id: u32,
+        image: Option<&str>,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.set_chat_profile_image(&ctx, chat, image).await?;
+
+            refresh_chat(session, account, &ctx, chat).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn leave_chat(&self, session: &Session, account_id: u32, chat_id: u32) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account.leave_chat(&ctx, chat).await?;
+
+            // the chat can't be written to anymore
+            refresh_chat(session, account, &ctx, chat).await
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn get_message_info(&self, account_id: u32, msg_id: u32) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let info = account.get_message_info(&ctx, MsgId::new(msg_id)).await?;
+
+            Ok(Response::MessageInfo(info))
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn get_contact_detail(&self, account_id: u32, contact_id: u32) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let contact = account.get_contact_detail(&ctx, contact_id).await?;
+
+            Ok(Response::ContactDetail(contact))
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn get_chat_members(&self, account_id: u32, chat_id: u32) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let members = account.get_chat_members(&ctx, ChatId::new(chat_id)).await?;
+
+            Ok(Response::ChatMembers { chat_id, members })
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn get_blocked_contacts(&self, account_id: u32) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let contacts = account.get_blocked_contacts(&ctx).await?;
+
+            Ok(Response::BlockedContacts {
+                account: account_id,
+                contacts,
             })
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn unblock_contact(&self, account_id: u32, contact_id: u32) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            account.unblock_contact(&ctx, contact_id).await?;
+
+            Ok(())
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn rename_contact(
+        &self,
+        account_id: u32,
+        contact_id: u32,
+        name: &str,
+    ) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            account
+                .rename_contact(&ctx, contact_id, name.trim())
+                .await?;
+            let contact = account.get_contact_detail(&ctx, contact_id).await?;
+
+            Ok(Response::ContactDetail(contact))
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn search_messages(
+        &self,
+        account_id: u32,
+        chat_id: Option<u32>,
+        query: String,
+    ) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let results = account
+                .search_messages(&ctx, chat_id.map(ChatId::new), &query)
+                .await?;
+
+            Ok(Response::SearchResults { query, results })
+        } else {
+            Err(invalid_account(account_id))
+        }
+    }
+
+    pub async fn set_draft(
+        &self,
+        account_id: u32,
+        chat_id: u32,
+        text: Option<String>,
+        quote_msg_id: Option<u32>,
+        file: Option<Attachment>,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some(account) = ls.account_states.get(&account_id) {
+            let ctx = ls.accounts.get_account(account_id).await.unwrap();
+            let chat = ChatId::new(chat_id);
+            account
+                .set_draft(&ctx, chat, text, quote_msg_id.map(MsgId::new), file)
+                .await?;
+
+            Ok(())
+        } else {
+            Err(invalid_account(account_id))
         }
     }
 
@@ -457,46 +894,99 @@ impl LocalState {
 
             Ok(())
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
         }
     }
 
-    pub async fn create_chat(&self, contacts: HashSet<u32>) -> Result<Response> {
+    pub async fn create_chat(&self, session: &Session, contacts: HashSet<u32>) -> Result<Response> {
+        let contact_id = match contacts.iter().exactly_one() {
+            Ok(contact_id) => *contact_id,
+            Err(_) => {
+                return Err(RequestError::new(
+                    ErrorKind::InvalidRequest,
+                    format!(
+                        "a 1:1 chat needs exactly one contact, got {}",
+                        contacts.len()
+                    ),
+                )
+                .into());
+            }
+        };
+
         let ls = self.inner.read().await;
-        if let Some((acc, ctx)) = ls.get_selected_account().await {
-            let chat = ChatId::create_for_contact(&ctx, *contacts.iter().next().unwrap()).await?;
-            acc.select_chat(&ctx, chat).await?;
-            let (chat_id, range, items, messages) = acc.load_message_list(&ctx, None).await?;
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+        if let Some((acc, ctx)) = ls.get_selected_account(session).await {
+            open_chat_with(session, acc, &ctx, contact_id).await
         } else {
-            Err(anyhow!("no selected account"))
+            Err(no_account_selected())
         }
     }
 
-    pub async fn create_group_chat(&self, contacts: HashSet<u32>, chat_name: &str) -> Result<Response> {
+    /// Creates a contact for the address and opens a 1:1 chat with it.
+    pub async fn create_contact(
+        &self,
+        session: &Session,
+        name: &str,
+        email: &str,
+    ) -> Result<Response> {
+        let email = email.trim();
+        if !contact::may_be_valid_addr(email) {
+            return Err(RequestError::new(
+                ErrorKind::InvalidRequest,
+                format!("invalid email address: {}", email),
+            )
+            .into());
+        }
+
         let ls = self.inner.read().await;
-        if let Some((acc, ctx)) = ls.get_selected_account().await {
+        if let Some((acc, ctx)) = ls.get_selected_account(session).await {
+            let contact_id = acc.create_contact(&ctx, name.trim(), email).await?;
+            open_chat_with(session, acc, &ctx, contact_id).await
+        } else {
+            Err(no_account_selected())
+        }
+    }
+
+    pub async fn create_group_chat(
+        &self,
+        session: &Session,
+        contacts: HashSet<u32>,
+        chat_name: &str,
+    ) -> Result<Response> {
+        let ls = self.inner.read().await;
+        if let Some((acc, ctx)) = ls.get_selected_account(session).await {
             let chat =
                 chat::create_group_chat(&ctx, ProtectionStatus::Unprotected, chat_name).await?;
 
-            for contact in contacts {
-                chat::add_contact_to_chat(&ctx, chat, contact).await;
+            // the group is created anyways, with the members that could be added
+            let mut failed = Vec::new();
+            for contact_id in contacts {
+                if let Err(err) = acc.add_chat_member(&ctx, chat, contact_id).await {
+                    warn!("{:#}", err);
+                    let addr = contact::Contact::load_from_db(&ctx, contact_id)
+                        .await
+                        .map(|contact| contact.get_addr().to_string())
+                        .unwrap_or_else(|_| contact_id.to_string());
+                    failed.push(addr);
+                }
+            }
+            let selected_chat = acc.select_chat(&ctx, chat).await?;
+            session
+                .state
+                .write()
+                .await
+                .select_chat(ctx.get_id(), chat, selected_chat);
+
+            let resp = load_message_list(session, acc, &ctx, chat, None).await?;
+            drop(ls);
+
+            if !failed.is_empty() {
+                let err = anyhow!("could not add {} to the group", failed.join(", "));
+                let mut ls = self.inner.write().await;
+                ls.push_error(ErrorKind::Other, Some(ctx.get_id()), None, false, &err);
             }
-            acc.select_chat(&ctx, chat).await?;
-            let (chat_id, range, items, messages) = acc.load_message_list(&ctx, None).await?;
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+            Ok(resp)
         } else {
-            Err(anyhow!("no selected account"))
+            Err(no_account_selected())
         }
     }
 
@@ -509,117 +999,149 @@ impl LocalState {
 
             Ok(())
         } else {
-            Err(anyhow!("invalid account: {}-{}", account_id, chat_id))
+            Err(invalid_account(account_id))
         }
     }
 
-    pub async fn load_chat_list(&self, start_index: usize, stop_index: usize) -> Result<Response> {
+    pub async fn load_chat_list(
+        &self,
+        session: &Session,
+        start_index: usize,
+        stop_index: usize,
+        query: Option<String>,
+        flags: ChatListFlags,
+    ) -> Result<Response> {
         let ls = self.inner.read().await;
-        if let Some((account, ctx)) = ls.get_selected_account().await {
+        if let Some((account, ctx)) = ls.get_selected_account(session).await {
             info!("Loading chat list");
-            match account.load_chat_list(&ctx, start_index, stop_index).await {
-                Ok((range, len, chats)) => Ok(Response::ChatList { range, len, chats }),
+            {
+                let mut session = session.state.write().await;
+                session.chat_list_range = Some((start_index, stop_index));
+                session.chat_list_query = query.clone();
+                session.chat_list_flags = flags;
+            }
+            let chat_list = account
+                .load_chat_list(&ctx, start_index, stop_index, query.as_deref(), flags)
+                .await;
+            match chat_list {
+                Ok((range, len, archived_len, chats)) => Ok(Response::ChatList {
+                    range,
+                    len,
+                    archived_len,
+                    chats,
+                }),
                 Err(err) => {
                     info!("Could not load chat list: {}", err);
                     // send an empty chat list to be handled by frontend
                     Ok(Response::ChatList {
                         range: (start_index, stop_index),
                         len: 0,
+                        archived_len: 0,
                         chats: Vec::new(),
                     })
                 }
             }
         } else {
-            Err(anyhow!("no selected account"))
+            Err(no_account_selected())
         }
     }
 
-    pub async fn send_contacts<T>(&self, writer: Arc<RwLock<T>>) -> Result<()>
+    pub async fn send_contacts<T>(&self, session: &Session, writer: Arc<RwLock<T>>) -> Result<()>
     where
-        T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static,
+        T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
         let ls = self.inner.read().await;
-        if let Some((_, ctx)) = ls.get_selected_account().await {
+        if let Some((_, ctx)) = ls.get_selected_account(session).await {
             let query: Option<&'static str> = None;
             let contact_ids = deltachat::contact::Contact::get_all(&ctx, 0, query).await?;
             info!("Contact-list: {:?}", contact_ids);
             let contacts = stream::iter(contact_ids)
-                .then(|id| Contact::load_from_db(&ctx, id))
-                .filter_map(|contact| async {
-                    match contact {
-                        Ok(contact) => Some(ContactInfo {
-                            id: contact.id,
-                            mail: contact.get_addr().to_owned(),
-                            display_name: contact.get_display_name().to_owned(),
-                        }),
-                        Err(_) => None,
-                    }
-                })
+                .then(|id| load_contact_info(&ctx, id))
+                .filter_map(|contact| async { contact.ok() })
                 .collect::<Vec<ContactInfo>>()
                 .await;
 
             send(writer, Response::Contacts(contacts)).await?;
             Ok(())
         } else {
-            Err(anyhow!("no selected account"))
+            Err(no_account_selected())
         }
     }
 
-    pub async fn load_message_list(&self, range: Option<(usize, usize)>) -> Result<Response> {
+    /// Loads the messages of the selected chat, by default in the range loaded last.
+    pub async fn load_message_list(
+        &self,
+        session: &Session,
+        range: Option<(usize, usize)>,
+    ) -> Result<Response> {
         let ls = self.inner.read().await;
-        if let Some((account, ctx)) = ls.get_selected_account().await {
-            let (chat_id, range, items, messages) = account.load_message_list(&ctx, range).await?;
-
-            Ok(Response::MessageList {
-                chat_id,
-                range,
-                items,
-                messages,
-            })
+        if let Some((account, ctx)) = ls.get_selected_account(session).await {
+            let range = match range {
+                Some(range) => Some(range),
+                None => session
+                    .state
+                    .read()
+                    .await
+                    .selected_chat(ctx.get_id())
+                    .and_then(|selected| selected.range),
+            };
+            load_selected_message_list(session, account, &ctx, range).await
         } else {
-            Err(anyhow!("no selected account"))
+            Err(no_account_selected())
         }
     }
 
-    pub async fn select_account(&self, account_id: u32) -> Result<Response> {
+    pub async fn select_account(&self, session: &Session, account_id: u32) -> Result<Response> {
         let mut ls = self.inner.write().await;
+        if !ls.account_states.contains_key(&account_id) {
+            return Err(invalid_account(account_id));
+        }
+        // remembered as the default for new sessions
         ls.select_account(account_id).await?;
-        if let Some((account, _ctx)) = ls.get_selected_account().await {
-            let state = account.state.read().await;
 
-            Ok(Response::Account {
-                account: account_id,
-                chat_id: state.selected_chat_id.map(|id| id.to_u32()),
-                chat: state.selected_chat.as_ref().cloned(),
-            })
-        } else {
-            Err(anyhow!("failed to select account"))
-        }
+        let mut session = session.state.write().await;
+        session.selected_account = Some(account_id);
+        session.chat_list_range = None;
+        let selected = session.selected_chat(account_id);
+
+        Ok(Response::Account {
+            account: account_id,
+            chat_id: selected.map(|selected| selected.id.to_u32()),
+            chat: selected.and_then(|selected| selected.chat.clone()),
+        })
     }
 
-    pub async fn send_text_message(&self, text: String) -> Result<()> {
+    pub async fn send_text_message(&self, session: &Session, text: String) -> Result<()> {
         let ls = self.inner.read().await;
-        if let Some((account, ctx)) = ls.get_selected_account().await {
-            account.send_text_message(&ctx, text).await?;
+        if let Some((account, ctx)) = ls.get_selected_account(session).await {
+            let chat_id = selected_chat_id(session, &ctx)
+                .await
+                .context("can not send message")?;
+            account.send_text_message(&ctx, chat_id, text).await?;
             Ok(())
         } else {
-            Err(anyhow!("no account selected"))
+            Err(no_account_selected())
         }
     }
 
     pub async fn send_file_message(
         &self,
+        session: &Session,
         typ: Viewtype,
         path: String,
         text: Option<String>,
         mime: Option<String>,
     ) -> Result<()> {
         let ls = self.inner.read().await;
-        if let Some((account, ctx)) = ls.get_selected_account().await {
+        if let Some((account, ctx)) = ls.get_selected_account(session).await {
+            let chat_id = selected_chat_id(session, &ctx)
+                .await
+                .context("can not send message")?;
             account
                 .send_file_message(
                     &ctx,
+                    chat_id,
                     Viewtype::from_i32(typ as i32).unwrap(),
                     path,
                     text,
@@ -628,7 +1150,28 @@ impl LocalState {
                 .await?;
             Ok(())
         } else {
-            Err(anyhow!("no account selected"))
+            Err(no_account_selected())
+        }
+    }
+
+    pub async fn send_reply(
+        &self,
+        session: &Session,
+        quote_msg_id: u32,
+        text: Option<String>,
+        file: Option<Attachment>,
+    ) -> Result<()> {
+        let ls = self.inner.read().await;
+        if let Some((account, ctx)) = ls.get_selected_account(session).await {
+            let chat_id = selected_chat_id(session, &ctx)
+                .await
+                .context("can not send message")?;
+            account
+                .send_reply(&ctx, chat_id, MsgId::new(quote_msg_id), text, file)
+                .await?;
+            Ok(())
+        } else {
+            Err(no_account_selected())
         }
     }
 
@@ -637,6 +1180,14 @@ impl LocalState {
         ls.accounts.maybe_network().await;
         Ok(())
     }
+
+    pub async fn dismiss_error(&self, id: u32) -> Result<()> {
+        let mut ls = self.inner.write().await;
+        let len = ls.errors.len();
+        ls.errors.retain(|err| err.id != id);
+        ensure!(ls.errors.len() != len, "unknown error: {}", id);
+        Ok(())
+    }
 }
 
 impl LocalStateInner {
@@ -662,69 +1213,92 @@ impl LocalStateInner {
             accounts,
             account_states,
             errors: Vec::new(),
+            next_error_id: 0,
         })
     }
 
-    pub async fn get_selected_account(&self) -> Option<(&Account, deltachat::context::Context)> {
-        if let Some(ctx) = self.accounts.get_selected_account().await {
-            let id = ctx.get_id();
-            if let Some(account) = self.account_states.get(&id) {
-                Some((account, ctx))
-            } else {
-                None
-            }
-        } else {
-            None
+    /// Remembers the error, with the address of the account as it may be removed after.
+    fn push_error(
+        &mut self,
+        kind: ErrorKind,
+        account: Option<u32>,
+        account_addr: Option<String>,
+        retryable: bool,
+        err: &anyhow::Error,
+    ) {
+        warn!("{:?} error for account {:?}: {:?}", kind, account, err);
+
+        self.next_error_id = self.next_error_id.wrapping_add(1);
+        self.errors.push(ErrorInfo {
+            id: self.next_error_id,
+            kind,
+            account,
+            account_addr,
+            timestamp: Utc::now(),
+            retryable,
+            message: format!("{:#}", err),
+        });
+
+        if self.errors.len() > MAX_ERRORS {
+            let excess = self.errors.len() - MAX_ERRORS;
+            self.errors.drain(..excess);
         }
     }
 
+    pub async fn get_selected_account(
+        &self,
+        session: &Session,
+    ) -> Option<(&Account, deltachat::context::Context)> {
+        let id = session.state.read().await.selected_account?;
+        let account = self.account_states.get(&id)?;
+        let ctx = self.accounts.get_account(id).await?;
+        Some((account, ctx))
+    }
+
     pub async fn select_account(&mut self, id: u32) -> Result<()> {
         self.accounts.select_account(id).await?;
         Ok(())
     }
 
-    pub async fn send_update<T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static>(
+    pub async fn send_update<T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static>(
         &self,
+        session: &Session,
         writer: Arc<RwLock<T>>,
     ) -> Result<()>
     where
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
-        let response = self.to_response().await;
+        let response = self.to_response(session).await;
         send(writer, response).await?;
         Ok(())
     }
 
-    pub async fn send_event<T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static>(
+    pub async fn send_event<T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static>(
         &self,
         writer: Arc<RwLock<T>>,
-        account: u32,
-        event: shared::Event,
+        event: RecordedEvent,
     ) -> Result<()>
     where
         T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
     {
-        send(writer, Response::Event { account, event }).await?;
+        let RecordedEvent {
+            seq,
+            account,
+            event,
+        } = event;
+        send(
+            writer,
+            Response::Event {
+                seq,
+                account,
+                event,
+            },
+        )
+        .await?;
         Ok(())
     }
 
-    pub async fn get_selected_account_id(&self) -> Option<u32> {
-        if let Some(ctx) = self.accounts.get_selected_account().await {
-            Some(ctx.get_id())
-        } else {
-            None
-        }
-    }
-
-    pub async fn get_selected_account_state(&self) -> Option<&Account> {
-        if let Some(id) = self.get_selected_account_id().await {
-            self.account_states.get(&id)
-        } else {
-            None
-        }
-    }
-
-    pub async fn to_response(&self) -> Response {
+    pub async fn to_response(&self, session: &Session) -> Response {
         let mut accounts = HashMap::with_capacity(self.account_states.len());
         for (id, account) in self.account_states.iter() {
             let account = &account.state.read().await;
@@ -750,23 +1324,20 @@ impl LocalStateInner {
             );
         }
 
-        let errors = self.errors.iter().map(|e| e.to_string()).collect();
-        let (selected_chat_id, selected_chat) =
-            if let Some(account) = self.get_selected_account_state().await {
-                let state = account.state.read().await;
-                (state.selected_chat_id.clone(), state.selected_chat.clone())
-            } else {
-                (None, None)
-            };
+        let errors = self.errors.clone();
+        let session = session.state.read().await;
+        let selected = session
+            .selected_account
+            .and_then(|id| session.selected_chat(id));
 
         Response::RemoteUpdate {
             state: State {
                 shared: SharedState {
                     accounts,
                     errors,
-                    selected_account: self.get_selected_account_id().await,
-                    selected_chat_id: selected_chat_id.map(|s| s.to_u32()),
-                    selected_chat,
+                    selected_account: session.selected_account,
+                    selected_chat_id: selected.map(|selected| selected.id.to_u32()),
+                    selected_chat: selected.and_then(|selected| selected.chat.clone()),
                 },
             },
         }
@@ -775,13 +1346,93 @@ impl LocalStateInner {
 
 pub async fn send<T>(writer: Arc<RwLock<T>>, response: Response) -> Result<()>
 where
-    T: futures::sink::Sink<Message> + Unpin + Sync + Send + 'static,
+    T: futures::sink::Sink<Response> + Unpin + Sync + Send + 'static,
     T::Error: std::fmt::Debug + std::error::Error + Send + Sync,
 {
     writer
         .write()
         .await
-        .send(Message::binary(bincode::serialize(&response).unwrap()))
+        .send(response)
         .await
         .map_err(Into::into)
 }
+
+async fn selected_chat_id(session: &Session, ctx: &Context) -> Result<ChatId> {
+    session
+        .state
+        .read()
+        .await
+        .selected_chat(ctx.get_id())
+        .map(|selected| selected.id)
+        .ok_or_else(no_chat_selected)
+}
+
+/// Loads the messages of the chat the session has selected in this account.
+async fn load_selected_message_list(
+    session: &Session,
+    account: &Account,
+    ctx: &Context,
+    range: Option<(usize, usize)>,
+) -> Result<Response> {
+    let chat_id = selected_chat_id(session, ctx)
+        .await
+        .context("failed to load message list")?;
+    load_message_list(session, account, ctx, chat_id, range).await
+}
+
+/// Reloads the chat, in case it is selected and its header shows what changed.
+async fn refresh_chat(
+    session: &Session,
+    account: &Account,
+    ctx: &Context,
+    chat: ChatId,
+) -> Result<()> {
+    let state = account.load_chat(ctx, chat).await?;
+    session
+        .state
+        .write()
+        .await
+        .refresh_chat(ctx.get_id(), chat, state);
+
+    Ok(())
+}
+
+/// Selects the 1:1 chat with the contact, creating it if needed.
+async fn open_chat_with(
+    session: &Session,
+    account: &Account,
+    ctx: &Context,
+    contact_id: u32,
+) -> Result<Response> {
+    let chat = ChatId::create_for_contact(ctx, contact_id).await?;
+    let selected_chat = account.select_chat(ctx, chat).await?;
+    session
+        .state
+        .write()
+        .await
+        .select_chat(ctx.get_id(), chat, selected_chat);
+
+    load_message_list(session, account, ctx, chat, None).await
+}
+
+async fn load_message_list(
+    session: &Session,
+    account: &Account,
+    ctx: &Context,
+    chat: ChatId,
+    range: Option<(usize, usize)>,
+) -> Result<Response> {
+    let (chat_id, range, items, messages) = account.load_message_list(ctx, chat, range).await?;
+    session
+        .state
+        .write()
+        .await
+        .set_message_list_range(ctx.get_id(), chat, range);
+
+    Ok(Response::MessageList {
+        chat_id,
+        range,
+        items,
+        messages,
+    })
+}
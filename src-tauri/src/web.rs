@@ -0,0 +1,82 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::{Context as _, Result};
+use async_std::sync::{Arc, Mutex};
+use log::*;
+use serde::Deserialize;
+use tide::{Body, Request, Response, StatusCode};
+
+use crate::auth::Secret;
+use crate::state::LocalState;
+
+#[derive(Debug, Clone)]
+struct WebState {
+    ws_port: u16,
+    secret: Secret,
+    /// Behind a mutex, as tide shares its state between threads.
+    local_state: Arc<Mutex<LocalState>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AssetQuery {
+    path: PathBuf,
+    token: String,
+}
+
+/// Serves the trunk-built frontend from `dist`, the port of the WebSocket backend under
+/// `/backend` and attachments under `/asset`, so the frontend works in ordinary browsers.
+pub async fn serve_web(
+    addr: SocketAddr,
+    dist: PathBuf,
+    ws_port: u16,
+    secret: Secret,
+    local_state: LocalState,
+) -> Result<()> {
+    let mut app = tide::with_state(WebState {
+        ws_port,
+        secret,
+        local_state: Arc::new(Mutex::new(local_state)),
+    });
+
+    app.at("/backend").get(backend);
+    app.at("/asset").get(asset);
+    app.at("/")
+        .serve_file(dist.join("index.html"))
+        .with_context(|| format!("missing frontend in {}", dist.display()))?;
+    app.at("/").serve_dir(&dist)?;
+
+    info!("Serving {} on http://{}", dist.display(), addr);
+    app.listen(addr).await?;
+
+    Ok(())
+}
+
+async fn backend(req: Request<WebState>) -> tide::Result {
+    Ok(Body::from_json(&serde_json::json!({ "port": req.state().ws_port }))?.into())
+}
+
+async fn asset(req: Request<WebState>) -> tide::Result {
+    let query: AssetQuery = req.query()?;
+    if !req.state().secret.verify(&query.token) {
+        return Ok(Response::new(StatusCode::Unauthorized));
+    }
+
+    // only hand out attachments and avatars, never the databases next to them
+    let blob_dirs = req.state().local_state.lock().await.blob_dirs().await;
+    let mut allowed = Vec::with_capacity(blob_dirs.len());
+    for dir in blob_dirs {
+        if let Ok(dir) = async_std::fs::canonicalize(dir).await {
+            allowed.push(dir);
+        }
+    }
+    let path = match async_std::fs::canonicalize(&query.path).await {
+        Ok(path) if allowed.iter().any(|dir| path.starts_with(dir)) => path,
+        _ => {
+            warn!("refusing to serve {}", query.path.display());
+            return Ok(Response::new(StatusCode::NotFound));
+        }
+    };
+
+    Ok(Body::from_file(path).await?.into())
+}